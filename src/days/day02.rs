@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
 
 pub struct Day;
 
@@ -55,29 +56,28 @@ impl Game {
 }
 
 impl FromStr for Game {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (id, bag_draws) = s.split_once(':').unwrap();
-        let id = id.split_once(' ').unwrap().1.parse::<usize>().unwrap();
-        let draws = bag_draws
-            .split(';')
-            .map(|s| s.trim())
-            .map(|s| s.split(',').map(|s| s.trim()))
-            .map(|pulls| {
-                let mut draw = (0, 0, 0);
-                for pull in pulls {
-                    let (count, color) = pull.split_once(' ').unwrap();
-                    match color {
-                        "red" => draw.0 = count.parse::<usize>().unwrap(),
-                        "green" => draw.1 = count.parse::<usize>().unwrap(),
-                        "blue" => draw.2 = count.parse::<usize>().unwrap(),
-                        _ => panic!("Unknown color {}", color),
-                    }
+        let mut cursor = Cursor::new(s);
+        cursor.literal("Game")?;
+        let id = cursor.uint()?;
+        cursor.literal(":")?;
+        let draws = cursor.sep_by(";", |cursor| {
+            let mut draw = (0, 0, 0);
+            cursor.sep_by(",", |cursor| {
+                let count = cursor.uint()?;
+                match cursor.word()? {
+                    "red" => draw.0 = count,
+                    "green" => draw.1 = count,
+                    "blue" => draw.2 = count,
+                    color => return Err(cursor.error(format!("unknown color {color}"))),
                 }
-                draw
-            })
-            .collect();
+                Ok(())
+            })?;
+            Ok(draw)
+        })?;
+        cursor.end()?;
         Ok(Game { id, draws })
     }
 }
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::parse::{offset_in, Cursor, ParseError};
 
 pub struct Day;
 
@@ -67,7 +68,7 @@ impl Plan {
 }
 
 impl FromStr for Plan {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let markers = s
@@ -89,44 +90,29 @@ impl FromStr for Plan {
         };
 
         // parse each line like "..452....45677..." into a list of numbers like [452, 45677]
-        let parts: Vec<Part> = s
-            .lines()
-            .enumerate()
-            .flat_map(|(row, line)| {
-                let mut chars = line.chars().enumerate().peekable();
-                let mut numbers = Vec::new();
-                let mut number = 0;
-                let mut start = usize::MAX;
-                while chars.peek().is_some() {
-                    let (col, c) = chars.next().unwrap();
-                    if c.is_numeric() {
-                        number = number * 10 + c.to_digit(10).unwrap() as usize;
-                        if start == usize::MAX {
-                            start = col;
-                        }
-                    } else if start != usize::MAX {
-                        numbers.push(Part {
-                            id: number,
-                            row,
-                            col: start,
-                            length: col - start,
-                        });
-                        number = 0;
-                        start = usize::MAX;
-                    }
+        let mut parts = Vec::new();
+        for (row, line) in s.lines().enumerate() {
+            let mut cursor = Cursor::new(line);
+            loop {
+                cursor.take_while(|c| !c.is_ascii_digit());
+                if cursor.peek().is_none() {
+                    break;
                 }
-                if start != usize::MAX {
-                    numbers.push(Part {
-                        id: number,
-                        row,
-                        col: start,
-                        length: line.len() - start,
-                    });
+                let col = cursor.pos();
+                let id = cursor
+                    .uint()
+                    .map_err(|err| err.shifted(offset_in(s, line)))?;
+                let part = Part {
+                    id,
+                    row,
+                    col,
+                    length: cursor.pos() - col,
+                };
+                if !plan.related_markers(&part).is_empty() {
+                    parts.push(part);
                 }
-                numbers
-            })
-            .filter(|part| !plan.related_markers(part).is_empty())
-            .collect();
+            }
+        }
 
         plan.parts.extend(parts);
         Ok(plan)
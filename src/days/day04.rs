@@ -1,6 +1,7 @@
 use std::{collections::HashSet, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
 
 pub struct Day;
 
@@ -39,20 +40,24 @@ struct Card {
 }
 
 impl FromStr for Card {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (_, numbers) = s.split_once(':').unwrap();
-        let (winning, actual) = numbers.split_once('|').unwrap();
-        let winning = winning
-            .split_whitespace()
-            .map(|s| s.parse::<usize>().unwrap())
+        let mut cursor = Cursor::new(s);
+        cursor.literal("Card")?;
+        cursor.uint::<usize>()?;
+        cursor.literal(":")?;
+        let winning = cursor
+            .many(Cursor::uint)
+            .into_iter()
             .collect::<HashSet<usize>>();
-        let winning_count = actual
-            .split_whitespace()
-            .map(|s| s.parse::<usize>().unwrap())
+        cursor.literal("|")?;
+        let winning_count = cursor
+            .many(Cursor::uint::<usize>)
+            .into_iter()
             .filter(|n| winning.contains(n))
             .count();
+        cursor.end()?;
         Ok(Card { winning_count })
     }
 }
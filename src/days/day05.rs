@@ -1,109 +1,141 @@
+use std::str::FromStr;
+
 use crate::solution::Solution;
+use crate::utils::parse::{blocks, fields, ints, lines, offset_in, pair, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let mut blocks = input.split("\n\n");
-        let seeds = blocks
-            .next()
-            .unwrap()
-            .split_once(':')
-            .unwrap()
-            .1
-            .split_whitespace()
-            .map(|s| s.parse::<usize>().unwrap())
-            .collect::<Vec<usize>>();
-        let mappings: Vec<(&str, Map)> = blocks.map(parse_mapping).collect();
-        let alm = Almanac {
-            seed_to_soil: mappings[0].1.clone(),
-            soil_to_fertilizer: mappings[1].1.clone(),
-            fertilizer_to_water: mappings[2].1.clone(),
-            water_to_light: mappings[3].1.clone(),
-            light_to_temperature: mappings[4].1.clone(),
-            temperature_to_humidity: mappings[5].1.clone(),
-            humidity_to_location: mappings[6].1.clone(),
-        };
-        let mut sorted = seeds
+        let alm: Almanac = input.parse().unwrap();
+        alm.seeds
             .iter()
-            .map(|seed| alm.map_seed_to_location(*seed))
-            .collect::<Vec<usize>>();
-        sorted.sort();
-
-        Some(sorted[0])
+            .map(|seed| alm.map("seed", "location", *seed))
+            .min()?
     }
 
-    fn part2(&self, _input: &str) -> Option<usize> {
-        None
+    fn part2(&self, input: &str) -> Option<usize> {
+        let alm: Almanac = input.parse().unwrap();
+        // Seeds come in `start length` pairs here, a leftover seed has no range
+        let pairs = alm.seeds.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return None;
+        }
+        let ranges = pairs.map(|pair| (pair[0], pair[0] + pair[1])).collect();
+        alm.map_ranges("seed", "location", ranges)?
+            .into_iter()
+            .map(|(start, _)| start)
+            .min()
     }
 }
 
-fn parse_mapping(block: &str) -> (&str, Map) {
-    let (name, rest) = block.split_once(':').unwrap();
-    let mappings = rest
-        .trim()
-        .lines()
-        .map(|line| {
-            let mut entries = line.split_whitespace().map(|s| s.parse::<usize>().unwrap());
-            Mapping {
-                dest: entries.next().unwrap(),
-                source: entries.next().unwrap(),
-                length: entries.next().unwrap(),
-            }
-        })
-        .collect::<Vec<Mapping>>();
-    (name, Map { mappings })
-}
-
+/// Seeds and the `x-to-y` maps between categories, in any number and order.
 #[derive(Debug, Clone)]
 struct Almanac {
-    pub seed_to_soil: Map,
-    pub soil_to_fertilizer: Map,
-    pub fertilizer_to_water: Map,
-    pub water_to_light: Map,
-    pub light_to_temperature: Map,
-    pub temperature_to_humidity: Map,
-    pub humidity_to_location: Map,
+    pub seeds: Vec<usize>,
+    pub maps: Vec<Map>,
 }
 
-impl Almanac {
-    pub fn map_seed_to_soil(&self, seed: usize) -> usize {
-        self.seed_to_soil.map(seed)
-    }
-
-    pub fn map_seed_to_fertilizer(&self, seed: usize) -> usize {
-        self.soil_to_fertilizer.map(self.map_seed_to_soil(seed))
-    }
-
-    pub fn map_seed_to_water(&self, seed: usize) -> usize {
-        self.fertilizer_to_water
-            .map(self.map_seed_to_fertilizer(seed))
-    }
+impl FromStr for Almanac {
+    type Err = ParseError;
 
-    pub fn map_seed_to_light(&self, seed: usize) -> usize {
-        self.water_to_light.map(self.map_seed_to_water(seed))
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut blocks = blocks(s);
+        let seeds = blocks
+            .next()
+            .ok_or_else(|| ParseError::new(0, "missing seeds"))?;
+        let seeds = ints(seeds).map_err(|e| e.shifted(offset_in(s, seeds)))?;
+        let maps = blocks
+            .map(|block| {
+                block
+                    .parse()
+                    .map_err(|e: ParseError| e.shifted(offset_in(s, block)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Almanac { seeds, maps })
     }
+}
 
-    pub fn map_seed_to_temperature(&self, seed: usize) -> usize {
-        self.light_to_temperature.map(self.map_seed_to_light(seed))
+impl Almanac {
+    /// Maps that lead from `from` to `to` when following the declared directions.
+    fn chain(&self, from: &str, to: &str) -> Option<Vec<&Map>> {
+        let mut chain = vec![];
+        let mut current = from;
+        while current != to {
+            if chain.len() == self.maps.len() {
+                return None;
+            }
+            let map = self.maps.iter().find(|map| map.source == current)?;
+            chain.push(map);
+            current = &map.dest;
+        }
+        Some(chain)
     }
 
-    pub fn map_seed_to_humidity(&self, seed: usize) -> usize {
-        self.temperature_to_humidity
-            .map(self.map_seed_to_temperature(seed))
+    /// Translates a single value from category `from` to category `to`.
+    ///
+    /// Only works along the declared directions, since the inverse of a map
+    /// can yield several values.
+    pub fn map(&self, from: &str, to: &str, value: usize) -> Option<usize> {
+        Some(
+            self.chain(from, to)?
+                .into_iter()
+                .fold(value, |value, map| map.map(value)),
+        )
     }
 
-    pub fn map_seed_to_location(&self, seed: usize) -> usize {
-        self.humidity_to_location
-            .map(self.map_seed_to_humidity(seed))
+    /// Translates half-open ranges `[start, end)` from category `from` to
+    /// category `to`. Against the declared direction this returns all
+    /// ranges that end up in the given ones, e.g. the seeds for a location.
+    pub fn map_ranges(
+        &self,
+        from: &str,
+        to: &str,
+        ranges: Vec<(usize, usize)>,
+    ) -> Option<Vec<(usize, usize)>> {
+        if let Some(chain) = self.chain(from, to) {
+            return Some(
+                chain
+                    .into_iter()
+                    .fold(ranges, |ranges, map| map.map_ranges(ranges)),
+            );
+        }
+        let chain = self.chain(to, from)?;
+        Some(
+            chain
+                .into_iter()
+                .rev()
+                .fold(ranges, |ranges, map| map.preimage_ranges(ranges)),
+        )
     }
 }
 
 #[derive(Debug, Clone)]
 struct Map {
+    source: String,
+    dest: String,
     mappings: Vec<Mapping>,
 }
 
+impl FromStr for Map {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, rest) = pair(s, ":")?;
+        let name = header
+            .strip_suffix(" map")
+            .ok_or_else(|| ParseError::new(0, "expected \"<source>-to-<dest> map:\""))?;
+        let (source, dest) = pair(name, "-to-")?;
+        let rest = rest.trim();
+        let mappings = lines(rest).map_err(|e| e.shifted(offset_in(s, rest)))?;
+        Ok(Map {
+            source: source.to_string(),
+            dest: dest.to_string(),
+            mappings,
+        })
+    }
+}
+
 impl Map {
     pub fn map(&self, index: usize) -> usize {
         for mapping in &self.mappings {
@@ -113,6 +145,63 @@ impl Map {
         }
         index
     }
+
+    /// Maps half-open ranges `[start, end)` as a whole, splitting them where
+    /// they only partially overlap a mapping.
+    pub fn map_ranges(&self, mut unmapped: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        let mut mapped = vec![];
+        for mapping in &self.mappings {
+            let source = mapping.source_range();
+            let mut remaining = vec![];
+            for range in unmapped {
+                match overlap(range, source) {
+                    Some((start, end)) => {
+                        mapped.push((mapping.map(start), mapping.map(end - 1) + 1));
+                        remaining.extend(subtract(range, source));
+                    }
+                    None => remaining.push(range),
+                }
+            }
+            unmapped = remaining;
+        }
+        mapped.extend(unmapped);
+        mapped
+    }
+
+    /// All source ranges that are mapped into the given ranges. Values outside
+    /// of every source range map to themselves, so they are part of the result
+    /// as well.
+    pub fn preimage_ranges(&self, ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+        let mut preimage = vec![];
+        for mapping in &self.mappings {
+            for range in &ranges {
+                if let Some((start, end)) = overlap(*range, mapping.dest_range()) {
+                    preimage.push((mapping.unmap(start), mapping.unmap(end - 1) + 1));
+                }
+            }
+        }
+        let identity = self.mappings.iter().fold(ranges, |ranges, mapping| {
+            ranges
+                .into_iter()
+                .flat_map(|range| subtract(range, mapping.source_range()))
+                .collect()
+        });
+        preimage.extend(identity);
+        preimage
+    }
+}
+
+fn overlap(a: (usize, usize), b: (usize, usize)) -> Option<(usize, usize)> {
+    let start = a.0.max(b.0);
+    let end = a.1.min(b.1);
+    (start < end).then_some((start, end))
+}
+
+/// The parts of `a` that are not covered by `b`.
+fn subtract(a: (usize, usize), b: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+    [(a.0, a.1.min(b.0)), (a.0.max(b.1), a.1)]
+        .into_iter()
+        .filter(|(start, end)| start < end)
 }
 
 #[derive(Debug, Clone)]
@@ -122,18 +211,45 @@ struct Mapping {
     length: usize,
 }
 
+impl FromStr for Mapping {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers: Vec<usize> = fields(s)?;
+        let [dest, source, length] = numbers[..] else {
+            return Err(ParseError::new(
+                0,
+                format!("expected 3 numbers, found {}", numbers.len()),
+            ));
+        };
+        Ok(Mapping {
+            dest,
+            source,
+            length,
+        })
+    }
+}
+
 impl Mapping {
     pub fn in_source(&self, index: usize) -> bool {
         index >= self.source && index < self.source + self.length
     }
 
-    pub fn _dest_range(&self) -> (usize, usize) {
-        (self.dest, self.dest + self.length - 1)
+    pub fn source_range(&self) -> (usize, usize) {
+        (self.source, self.source + self.length)
+    }
+
+    pub fn dest_range(&self) -> (usize, usize) {
+        (self.dest, self.dest + self.length)
     }
 
     pub fn map(&self, index: usize) -> usize {
         self.dest + (index - self.source)
     }
+
+    pub fn unmap(&self, index: usize) -> usize {
+        self.source + (index - self.dest)
+    }
 }
 
 #[cfg(test)]
@@ -154,15 +270,55 @@ mod tests {
     }
 
     #[test]
-    #[ignore = "Not yet implemented"]
     fn test_part2_example() {
         let input = read_input(5, true, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(46))
     }
     #[test]
-    #[ignore = "Not yet implemented"]
     fn test_part2_challenge() {
         let input = read_input(5, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(12634632))
     }
+
+    #[test]
+    fn test_named_chain() {
+        let alm: Almanac = read_input(5, true, 1).unwrap().parse().unwrap();
+        assert_eq!(alm.map("seed", "soil", 79), Some(81));
+        assert_eq!(alm.map("fertilizer", "light", 81), Some(74));
+        assert_eq!(alm.map("soil", "seed", 81), None);
+        assert_eq!(alm.map("seed", "unknown", 1), None);
+
+        let seeds = alm.map_ranges("location", "seed", vec![(46, 47)]).unwrap();
+        assert!(seeds
+            .iter()
+            .any(|(start, end)| (*start..*end).contains(&82)));
+        for (start, end) in seeds {
+            for seed in start..end {
+                assert_eq!(alm.map("seed", "location", seed), Some(46));
+            }
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_stages() {
+        let alm: Almanac = "seeds: 1 5\n\nb-to-c map:\n10 0 3\n\na-to-b map:\n0 5 5"
+            .parse()
+            .unwrap();
+        assert_eq!(alm.map("a", "c", 6), Some(11));
+        assert_eq!(alm.map("a", "c", 2), Some(12));
+        assert_eq!(
+            alm.map_ranges("a", "c", vec![(1, 6)]),
+            Some(vec![(10, 11), (11, 13), (3, 5)])
+        );
+        let mut preimage = alm.map_ranges("c", "a", vec![(10, 12)]).unwrap();
+        preimage.sort();
+        assert_eq!(preimage, vec![(0, 2), (5, 7), (10, 12)]);
+    }
+
+    #[test]
+    fn test_odd_seed_count() {
+        let input = "seeds: 79 14 55\n\nseed-to-location map:\n0 50 100";
+        assert_eq!(Day.part1(input), Some(5));
+        assert_eq!(Day.part2(input), None);
+    }
 }
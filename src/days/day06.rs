@@ -1,10 +1,13 @@
+use std::num::ParseIntError;
+
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let races = parse_input(input);
+        let races = parse_input(input).unwrap();
         /*
            s_a(t) = t * (a-t)
            r = -t^2 + a*t
@@ -21,12 +24,7 @@ impl Solution for Day {
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
-        let lines: Vec<i64> = input
-            .lines()
-            .map(|line| line.split_once(':').unwrap().1.trim().replace(' ', ""))
-            .map(|s| s.parse::<i64>().unwrap())
-            .collect();
-        let race = (lines[0], lines[1]);
+        let race = parse_kerned(input).unwrap();
         let win_range = race_win_range(&race);
         Some((win_range.1 - win_range.0 + 1) as usize)
     }
@@ -46,15 +44,45 @@ fn pq_formula(p: i64, q: i64) -> (i64, i64) {
     ((min.floor() + 1.) as i64, (max.ceil() - 1.) as i64)
 }
 
-fn parse_input(input: &str) -> Vec<(i64, i64)> {
-    let mut iters = input.lines().map(|line| {
-        line.split_whitespace()
-            .skip(1)
-            .map(|s| s.parse::<i64>().unwrap())
-    });
-    let times = iters.next().unwrap();
-    let distances = iters.next().unwrap();
-    times.zip(distances).collect()
+fn parse_input(input: &str) -> Result<Vec<(i64, i64)>, ParseError> {
+    let mut cursor = Cursor::new(input);
+    cursor.literal("Time:")?;
+    let times = cursor.many(|cursor| cursor.uint());
+    let at = cursor.pos();
+    cursor.literal("Distance:")?;
+    let distances = cursor.many(|cursor| cursor.uint());
+    cursor.end()?;
+    if times.len() != distances.len() {
+        return Err(ParseError::new(
+            at,
+            format!(
+                "expected {} distances, found {}",
+                times.len(),
+                distances.len()
+            ),
+        ));
+    }
+    Ok(times.into_iter().zip(distances).collect())
+}
+
+/// Reads both lines as a single number each, ignoring the spaces between the digits.
+fn parse_kerned(input: &str) -> Result<(i64, i64), ParseError> {
+    let mut cursor = Cursor::new(input);
+    let time = kerned(&mut cursor, "Time:")?;
+    let distance = kerned(&mut cursor, "Distance:")?;
+    cursor.end()?;
+    Ok((time, distance))
+}
+
+fn kerned(cursor: &mut Cursor, label: &str) -> Result<i64, ParseError> {
+    cursor.literal(label)?;
+    cursor.skip_whitespace();
+    let start = cursor.pos();
+    let digits = cursor.take_while(|c| c.is_ascii_digit() || c == ' ');
+    digits
+        .replace(' ', "")
+        .parse()
+        .map_err(|err: ParseIntError| ParseError::from(err).shifted(start))
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 use std::{collections::HashMap, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
 
 pub struct Day;
 
@@ -71,7 +72,7 @@ impl Hand {
             return Type::FiveOfAKind;
         }
         let mut counts = counts.into_iter().collect::<Vec<_>>();
-        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        counts.sort_by_key(|c| std::cmp::Reverse(c.1));
         let (_card, count) = counts[0];
         if jokers > 0 {
             let count = count + jokers;
@@ -115,15 +116,17 @@ impl Hand {
 }
 
 impl FromStr for Hand {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (cards, bid) = s.split_once(' ').unwrap();
-        let cards = cards
+        let mut cursor = Cursor::new(s);
+        let cards = cursor
+            .word()?
             .chars()
             .map(|s| s.into())
             .collect::<Vec<Card>>();
-        let bid = bid.parse::<usize>().unwrap();
+        let bid = cursor.uint()?;
+        cursor.end()?;
         Ok(Self::new(cards, bid))
     }
 }
@@ -1,16 +1,15 @@
 use std::collections::HashMap;
 
 use crate::solution::Solution;
+use crate::utils::cycle::find_cycle;
+use crate::utils::math::lcm;
+use crate::utils::parse::{offset_in, pair, Cursor, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let (path, rest) = input
-            .split_once("\n\n")
-            .unwrap();
-        let map: Map = rest.into();
-        let path: Vec<Direction> = path.chars().map(|c| c.into()).collect();
+        let (path, map) = parse_input(input).unwrap();
         if map.mapping.contains_key("AAA") {
             let mut curr = "AAA";
             for i in 0..100_000_000 {
@@ -26,69 +25,44 @@ impl Solution for Day {
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
-        let (path, rest) = input
-            .split_once("\n\n")
-            .unwrap();
-        let map: Map = rest.into();
-        let path: Vec<Direction> = path.chars().map(|c| c.into()).collect();
+        let (path, map) = parse_input(input).unwrap();
         let starts: Vec<&str> = map
             .mapping
             .keys()
             .filter(|k| k.ends_with('A'))
             .map(|s| &s[..])
             .collect();
-        let mut loops = Vec::new();
-        let mut z_pos = HashMap::new();
-
-        for start in starts.clone() {
-            let mut loop_detector: HashMap<(&str, Direction, usize), usize> = HashMap::new();
-            let mut curr = start;
-            z_pos.insert(start, Vec::new());
-            for i in 0..100_000 {
-                if curr.ends_with('Z') {
-                    z_pos.get_mut(start).unwrap().push(i);
-                }
-                let path_pos = i % path.len();
-                let dir = path[path_pos];
-                let state = (curr, dir, path_pos);
-                if let Some(loop_start) = loop_detector.get(&state) {
-                    loops.push((
-                        start,
-                        *loop_start,
-                        z_pos
-                            .get(start)
-                            .expect(&("Could not find z_pos for ".to_owned() + start))
-                            .clone(),
-                        i - loop_start,
-                    ));
-                    break;
-                }
-                loop_detector.insert(state, i);
-                curr = map.step(curr, dir);
-            }
-        }
-
-        // All loops only contain one Z element, which is also the same as the loop length.
-        // For this reason you just need to calculate the least common multiple of all these values
-        assert!(loops
+        let loop_lengths: Vec<usize> = starts
             .iter()
-            .all(|looped| looped.2.iter().any(|l| l.eq(&looped.3))));
-        let loop_lengths: Vec<usize> = loops.iter().map(|looped| looped.3).collect();
+            .map(|start| {
+                let cycle = find_cycle((*start, 0), |state| map.walk(&path, state), |state| *state);
+                let mut state = (*start, 0);
+                for _ in 0..cycle.period {
+                    map.walk(&path, &mut state);
+                }
+                // All loops only contain one Z element, which is also the same as the loop length.
+                // For this reason you just need to calculate the least common multiple of all these values
+                assert!(state.0.ends_with('Z'));
+                cycle.period
+            })
+            .collect();
         let lcm = loop_lengths.iter().fold(1, |acc, &x| lcm(acc, x));
         Some(lcm)
     }
 }
 
-pub fn lcm(a: usize, b: usize) -> usize {
-    a * b / gcd(a, b)
-}
-
-pub fn gcd(a: usize, b: usize) -> usize {
-    if b == 0 {
-        a
-    } else {
-        gcd(b, a % b)
-    }
+fn parse_input(input: &str) -> Result<(Vec<Direction>, Map<'_>), ParseError> {
+    let (path, rest) = pair(input, "\n\n")?;
+    let path = path
+        .char_indices()
+        .map(|(i, c)| match c.to_ascii_lowercase() {
+            'l' => Ok(Direction::Left),
+            'r' => Ok(Direction::Right),
+            _ => Err(ParseError::new(i, format!("invalid direction {c:?}"))),
+        })
+        .collect::<Result<_, _>>()?;
+    let map = Map::try_from(rest).map_err(|err| err.shifted(offset_in(input, rest)))?;
+    Ok((path, map))
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -97,16 +71,6 @@ enum Direction {
     Right,
 }
 
-impl From<char> for Direction {
-    fn from(value: char) -> Self {
-        match value.to_ascii_lowercase() {
-            'l' => Self::Left,
-            'r' => Self::Right,
-            _ => unreachable!("Invalid direction"),
-        }
-    }
-}
-
 #[derive(Debug)]
 struct Map<'a> {
     mapping: HashMap<&'a str, Mapping<'a>>,
@@ -116,18 +80,27 @@ impl<'a> Map<'a> {
     pub fn step(&'a self, from: &str, dir: Direction) -> &'a str {
         self.mapping.get(from).unwrap().step(dir)
     }
+
+    /// Advances a `(node, path position)` state by one step along `path`.
+    pub fn walk(&'a self, path: &[Direction], state: &mut (&'a str, usize)) {
+        state.0 = self.step(state.0, path[state.1]);
+        state.1 = (state.1 + 1) % path.len();
+    }
 }
 
-impl<'a> From<&'a str> for Map<'a> {
-    fn from(value: &'a str) -> Self {
-        Self {
-            mapping: HashMap::from_iter(
-                value
-                    .lines()
-                    .map(Mapping::from)
-                    .map(|mapping| (mapping.from, mapping)),
-            ),
-        }
+impl<'a> TryFrom<&'a str> for Map<'a> {
+    type Error = ParseError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let mapping = value
+            .lines()
+            .map(|line| {
+                Mapping::try_from(line)
+                    .map(|mapping| (mapping.from, mapping))
+                    .map_err(|err| err.shifted(offset_in(value, line)))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { mapping })
     }
 }
 
@@ -147,11 +120,21 @@ impl<'a> Mapping<'a> {
     }
 }
 
-impl<'a> From<&'a str> for Mapping<'a> {
-    fn from(value: &'a str) -> Self {
-        let (from, rest) = value.split_once(" = ").unwrap();
-        let (left, right) = rest[1..rest.len() - 1].split_once(", ").unwrap();
-        Self { from, left, right }
+impl<'a> TryFrom<&'a str> for Mapping<'a> {
+    type Error = ParseError;
+
+    /// Parses `AAA = (BBB, CCC)`.
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let mut cursor = Cursor::new(value);
+        let from = cursor.word()?;
+        cursor.literal("=")?;
+        cursor.literal("(")?;
+        let left = cursor.word()?;
+        cursor.literal(",")?;
+        let right = cursor.word()?;
+        cursor.literal(")")?;
+        cursor.end()?;
+        Ok(Self { from, left, right })
     }
 }
 
@@ -181,4 +164,11 @@ mod tests {
         let input = read_input(8, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(10371555451871));
     }
+
+    #[test]
+    fn test_parse_error() {
+        let err = parse_input("LR\n\nAAA = (BBB, CCC)\nBBB = (AAA CCC)").unwrap_err();
+        assert_eq!(err.offset, 32);
+        assert!(parse_input("LX\n\nAAA = (BBB, CCC)").is_err());
+    }
 }
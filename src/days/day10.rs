@@ -1,32 +1,29 @@
 use std::{fmt::Display, str::FromStr};
 
-use crate::solution::Solution;
+use crate::solution::{Solution, VisualizeOptions};
+use crate::utils::parse::{grid, ParseError};
+use crate::utils::polygon::Polygon;
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
         let map: Map = input.parse().unwrap();
-        let length = map.loop_length();
-        Some(length / 2)
+        Some(map.trace_loop().len() / 2)
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
         let map: Map = input.parse().unwrap();
-        let extract = map.extract_loop();
-        let mut enlarged = extract.enlarge();
-        enlarged.remove_outside();
-        let shrunk = enlarged.shrink();
-        let empties = shrunk
-            .map
-            .iter()
-            .map(|row| row.iter().filter(|pipe| **pipe == Pipe::Empty).count())
-            .sum::<usize>();
-        Some(empties)
+        Some(map.enclosed_tiles())
+    }
+
+    fn visualize(&self, input: &str, options: &VisualizeOptions) -> Option<String> {
+        let map: Map = input.parse().ok()?;
+        Some(map.render(!options.plain))
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Up,
     Down,
@@ -34,172 +31,71 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn opposite(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Map {
     map: Vec<Vec<Pipe>>,
     start: (usize, usize),
+    /// The pipe hidden under the start tile.
+    start_pipe: Pipe,
+    /// Tiles of the main loop in order, beginning at the start.
+    main_loop: Vec<(usize, usize)>,
 }
 
 impl Map {
-    pub fn loop_length(&self) -> usize {
-        let mut current = self.start;
-        let mut dir: Direction = *[
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-        ]
-        .iter()
-        .find(|dir| self.can_move(current, **dir))
-        .unwrap();
-        let mut length = 0;
-        loop {
-            length += 1;
-            current = self.pos_in_dir(current, dir);
-            if current == self.start {
-                break;
-            }
-            dir = self.map[current.0][current.1].other_dir(dir);
+    /// The pipe at `pos`, with the start tile replaced by its actual pipe.
+    pub fn pipe_at(&self, pos: (usize, usize)) -> Pipe {
+        match self.map[pos.0][pos.1] {
+            Pipe::Start => self.start_pipe,
+            pipe => pipe,
         }
-        length
     }
 
-    pub fn extract_loop(&self) -> Self {
+    /// Tiles of the main loop in order, beginning at the start.
+    pub fn trace_loop(&self) -> &[(usize, usize)] {
+        &self.main_loop
+    }
+
+    /// Follows the pipes from the start, assuming `start_pipe` under it, and
+    /// returns the visited tiles if that leads back to the start.
+    fn follow(&self, start_pipe: Pipe) -> Option<Vec<(usize, usize)>> {
+        let mut dir = start_pipe.connections()?[0];
         let mut current = self.start;
-        let mut dir: Direction = *[
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-        ]
-        .iter()
-        .find(|dir| self.can_move(current, **dir))
-        .unwrap();
-        let mut extract = Map {
-            map: vec![vec![Pipe::Empty; self.map[0].len()]; self.map.len()],
-            start: self.start,
-        };
+        let mut tiles = vec![];
         loop {
-            extract.map[current.0][current.1] = self.map[current.0][current.1];
+            tiles.push(current);
+            if !self.pos_dir_possible(current, dir) {
+                return None;
+            }
             current = self.pos_in_dir(current, dir);
             if current == self.start {
-                break;
+                return start_pipe.connects(dir.opposite()).then_some(tiles);
             }
-            dir = self.map[current.0][current.1].other_dir(dir);
+            dir = self.pipe_at(current).other_dir(dir)?;
         }
-        extract
     }
 
-    pub fn enlarge(&self) -> Self {
-        let mut enlarged = Map {
-            map: vec![vec![Pipe::Empty; self.map[0].len() * 2]; self.map.len() * 2],
-            start: (self.start.0 * 2, self.start.1 * 2),
-        };
-
-        for row in 0..self.map.len() {
-            for col in 0..self.map[0].len() {
-                let pipe = self.map[row][col];
-                let row = row * 2;
-                let col = col * 2;
-                enlarged.map[row][col] = pipe;
-                match pipe {
-                    Pipe::CornerBottomLeft => {
-                        enlarged.map[row + 1][col] = Pipe::Vertical;
-                    }
-                    Pipe::CornerBottomRight => {
-                        enlarged.map[row + 1][col] = Pipe::Vertical;
-                        enlarged.map[row][col + 1] = Pipe::Horizontal;
-                    }
-                    Pipe::CornerTopLeft => {}
-                    Pipe::CornerTopRight => {
-                        enlarged.map[row][col + 1] = Pipe::Horizontal;
-                    }
-                    Pipe::Horizontal => {
-                        enlarged.map[row][col + 1] = Pipe::Horizontal;
-                    }
-                    Pipe::Vertical => {
-                        enlarged.map[row + 1][col] = Pipe::Vertical;
-                    }
-                    Pipe::Start => {
-                        if self.map[(row / 2) + 1][col / 2] == Pipe::Vertical
-                            || self.map[(row / 2) + 1][col / 2] == Pipe::CornerBottomLeft
-                            || self.map[(row / 2) + 1][col / 2] == Pipe::CornerBottomRight
-                        {
-                            enlarged.map[row + 1][col] = Pipe::Vertical;
-                        }
-                        if self.map[row / 2][(col / 2) + 1] == Pipe::Horizontal
-                            || self.map[row / 2][(col / 2) + 1] == Pipe::CornerBottomLeft
-                            || self.map[row / 2][(col / 2) + 1] == Pipe::CornerTopLeft
-                        {
-                            enlarged.map[row][col + 1] = Pipe::Horizontal;
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        enlarged
-    }
-
-    pub fn shrink(&self) -> Self {
-        let mut shrunk = Map {
-            map: vec![vec![Pipe::Empty; self.map[0].len() / 2]; self.map.len() / 2],
-            start: (self.start.0 / 2, self.start.1 / 2),
-        };
-
-        for row in 0..self.map.len() / 2 {
-            for col in 0..self.map[0].len() / 2 {
-                let pipe = self.map[row * 2][col * 2];
-                shrunk.map[row][col] = pipe;
-            }
-        }
-
-        shrunk
-    }
-
-    pub fn remove_outside(&mut self) {
-        let mut pending = vec![];
-        for row in 0..self.map.len() {
-            if self.map[row][0] == Pipe::Empty {
-                pending.push((row, 0));
-            }
-            if self.map[row][self.map[0].len() - 1] == Pipe::Empty {
-                pending.push((row, self.map[0].len() - 1));
-            }
-        }
-        for col in 0..self.map[0].len() {
-            if self.map[0][col] == Pipe::Empty {
-                pending.push((0, col));
-            }
-            if self.map[self.map.len() - 1][col] == Pipe::Empty {
-                pending.push((self.map.len() - 1, col));
-            }
-        }
-
-        while let Some((row, col)) = pending.pop() {
-            let pipe = self.map[row][col];
-            // println!("Checking {:?} {:?}", (row, col), pipe);
-            if pipe == Pipe::Outside {
-                continue;
-            }
-            self.map[row][col] = Pipe::Outside;
-            for dir in [
-                Direction::Up,
-                Direction::Down,
-                Direction::Left,
-                Direction::Right,
-            ]
-            .iter()
-            .filter(|dir| self.pos_dir_possible((row, col), **dir))
-            {
-                let pos = self.pos_in_dir((row, col), *dir);
-                let next_pipe = self.map[pos.0][pos.1];
-                if next_pipe == Pipe::Empty {
-                    pending.push(pos);
-                }
-            }
-        }
+    /// Tiles enclosed by the main loop. The loop is a simple lattice polygon
+    /// through the tile centres, so its area from the shoelace formula and
+    /// Pick's theorem give the number of lattice points inside.
+    pub fn enclosed_tiles(&self) -> usize {
+        let polygon = Polygon::new(
+            self.trace_loop()
+                .iter()
+                .map(|&(row, col)| (col as i64, row as i64))
+                .collect(),
+        );
+        polygon.interior_points().unwrap_or(0) as usize
     }
 
     pub fn pos_in_dir(&self, pos: (usize, usize), dir: Direction) -> (usize, usize) {
@@ -220,55 +116,110 @@ impl Map {
         }
     }
 
+    /// Whether the pipe next to `pos` in direction `dir` connects back to it.
     pub fn can_move(&self, pos: (usize, usize), dir: Direction) -> bool {
         if !self.pos_dir_possible(pos, dir) {
             return false;
         }
         let next = self.pos_in_dir(pos, dir);
-        let next_pipe = self.map[next.0][next.1];
-        // println!("Can move {:?} from {:?} to {:?} ({:?})", dir, pos, next, next_pipe);
-        match next_pipe {
-            Pipe::Empty => false,
-            _ => matches!(
-                (dir, next_pipe),
-                (Direction::Up, Pipe::CornerBottomLeft)
-                    | (Direction::Up, Pipe::CornerBottomRight)
-                    | (Direction::Down, Pipe::CornerTopLeft)
-                    | (Direction::Down, Pipe::CornerTopRight)
-                    | (Direction::Left, Pipe::CornerTopRight)
-                    | (Direction::Left, Pipe::CornerBottomRight)
-                    | (Direction::Right, Pipe::CornerTopLeft)
-                    | (Direction::Right, Pipe::CornerBottomLeft)
-                    | (Direction::Up, Pipe::Vertical)
-                    | (Direction::Down, Pipe::Vertical)
-                    | (Direction::Left, Pipe::Horizontal)
-                    | (Direction::Right, Pipe::Horizontal)
-            ),
-        }
+        self.map[next.0][next.1].connects(dir.opposite())
     }
 }
 
 impl FromStr for Map {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut start = (0, 0);
-        let map = s
-            .lines()
+        let map: Vec<Vec<Pipe>> = grid(s)?;
+        let start = map
+            .iter()
+            .enumerate()
+            .find_map(|(row, pipes)| {
+                pipes
+                    .iter()
+                    .position(|p| *p == Pipe::Start)
+                    .map(|col| (row, col))
+            })
+            .ok_or_else(|| ParseError::new(0, "map has no start pipe"))?;
+        let mut map = Map {
+            map,
+            start,
+            start_pipe: Pipe::Empty,
+            main_loop: vec![],
+        };
+        // Several neighbours may point at the start, so pick the shape that
+        // actually closes a loop
+        (map.start_pipe, map.main_loop) = Pipe::SHAPES
+            .into_iter()
+            .filter(|pipe| {
+                pipe.connections()
+                    .is_some_and(|dirs| dirs.iter().all(|dir| map.can_move(start, *dir)))
+            })
+            .find_map(|pipe| map.follow(pipe).map(|tiles| (pipe, tiles)))
+            .ok_or_else(|| ParseError::new(0, "start is not part of a loop"))?;
+        Ok(map)
+    }
+}
+
+impl Map {
+    /// For every tile, whether it belongs to the main loop.
+    fn loop_mask(&self) -> Vec<Vec<bool>> {
+        let mut mask = vec![vec![false; self.map[0].len()]; self.map.len()];
+        for &(row, col) in self.trace_loop() {
+            mask[row][col] = true;
+        }
+        mask
+    }
+
+    /// For every tile, whether it is enclosed by the main loop given as
+    /// [`Map::loop_mask`]. Scanning a row from the left, every loop pipe that
+    /// opens upwards is a crossing.
+    fn inside_tiles(&self, on_loop: &[Vec<bool>]) -> Vec<Vec<bool>> {
+        on_loop
+            .iter()
             .enumerate()
-            .map(|(row, l)| {
-                l.chars()
+            .map(|(row, on_loop)| {
+                let mut inside = false;
+                on_loop
+                    .iter()
                     .enumerate()
-                    .map(|(col, c)| {
-                        let p = Pipe::from(c);
-                        if p == Pipe::Start {
-                            start = (row, col);
+                    .map(|(col, on_loop)| {
+                        if *on_loop {
+                            inside ^= self.pipe_at((row, col)).connects(Direction::Up);
+                            false
+                        } else {
+                            inside
                         }
-                        p
                     })
-                    .collect::<Vec<Pipe>>()
+                    .collect()
             })
-            .collect::<Vec<Vec<Pipe>>>();
-        Ok(Map { map, start })
+            .collect()
+    }
+
+    /// The main loop in box-drawing characters. Other tiles, junk pipes
+    /// included, show `I` when enclosed and `O` otherwise. With `ansi`, the
+    /// start is highlighted and the enclosed area shaded instead.
+    pub fn render(&self, ansi: bool) -> String {
+        let on_loop = self.loop_mask();
+        let inside = self.inside_tiles(&on_loop);
+        let mut rendered = String::new();
+        for (row, (on_loop, inside)) in on_loop.iter().zip(&inside).enumerate() {
+            for (col, (on_loop, inside)) in on_loop.iter().zip(inside).enumerate() {
+                let glyph = self.pipe_at((row, col)).glyph();
+                match (ansi, *on_loop, *inside) {
+                    (true, true, _) if (row, col) == self.start => {
+                        rendered.push_str(&format!("\x1b[1;33m{glyph}\x1b[0m"))
+                    }
+                    (true, true, _) => rendered.push_str(&format!("\x1b[36m{glyph}\x1b[0m")),
+                    (true, false, true) => rendered.push_str("\x1b[42m \x1b[0m"),
+                    (true, false, false) => rendered.push(' '),
+                    (false, true, _) => rendered.push(glyph),
+                    (false, false, true) => rendered.push('I'),
+                    (false, false, false) => rendered.push('O'),
+                }
+            }
+            rendered.push('\n');
+        }
+        rendered
     }
 }
 
@@ -294,25 +245,60 @@ enum Pipe {
     CornerTopLeft,
     CornerBottomRight,
     CornerBottomLeft,
-    Outside,
 }
 
 impl Pipe {
-    pub fn other_dir(&self, dir: Direction) -> Direction {
-        match (self, dir) {
-            (Pipe::CornerBottomLeft, Direction::Up) => Direction::Left,
-            (Pipe::CornerBottomLeft, Direction::Right) => Direction::Down,
-            (Pipe::CornerBottomRight, Direction::Up) => Direction::Right,
-            (Pipe::CornerBottomRight, Direction::Left) => Direction::Down,
-            (Pipe::CornerTopLeft, Direction::Down) => Direction::Left,
-            (Pipe::CornerTopLeft, Direction::Right) => Direction::Up,
-            (Pipe::CornerTopRight, Direction::Down) => Direction::Right,
-            (Pipe::CornerTopRight, Direction::Left) => Direction::Up,
-            (Pipe::Horizontal, Direction::Left) => Direction::Left,
-            (Pipe::Horizontal, Direction::Right) => Direction::Right,
-            (Pipe::Vertical, Direction::Up) => Direction::Up,
-            (Pipe::Vertical, Direction::Down) => Direction::Down,
-            _ => panic!("Invalid pipe/dir combination: {:?} {:?}", self, dir),
+    /// Every pipe that connects two directions.
+    const SHAPES: [Pipe; 6] = [
+        Pipe::Horizontal,
+        Pipe::Vertical,
+        Pipe::CornerTopRight,
+        Pipe::CornerTopLeft,
+        Pipe::CornerBottomRight,
+        Pipe::CornerBottomLeft,
+    ];
+
+    /// The two directions the pipe opens to.
+    pub fn connections(&self) -> Option<[Direction; 2]> {
+        match self {
+            Pipe::Horizontal => Some([Direction::Left, Direction::Right]),
+            Pipe::Vertical => Some([Direction::Up, Direction::Down]),
+            Pipe::CornerTopRight => Some([Direction::Up, Direction::Right]),
+            Pipe::CornerTopLeft => Some([Direction::Up, Direction::Left]),
+            Pipe::CornerBottomRight => Some([Direction::Down, Direction::Right]),
+            Pipe::CornerBottomLeft => Some([Direction::Down, Direction::Left]),
+            Pipe::Empty | Pipe::Start => None,
+        }
+    }
+
+    /// Box-drawing character for the pipe.
+    pub fn glyph(&self) -> char {
+        match self {
+            Pipe::Empty => ' ',
+            Pipe::Start => 'S',
+            Pipe::Horizontal => '─',
+            Pipe::Vertical => '│',
+            Pipe::CornerTopRight => '└',
+            Pipe::CornerTopLeft => '┘',
+            Pipe::CornerBottomRight => '┌',
+            Pipe::CornerBottomLeft => '┐',
+        }
+    }
+
+    pub fn connects(&self, dir: Direction) -> bool {
+        self.connections().is_some_and(|dirs| dirs.contains(&dir))
+    }
+
+    /// Direction a pipe leads to after entering it while moving in `dir`.
+    pub fn other_dir(&self, dir: Direction) -> Option<Direction> {
+        let [a, b] = self.connections()?;
+        let entered = dir.opposite();
+        if entered == a {
+            Some(b)
+        } else if entered == b {
+            Some(a)
+        } else {
+            None
         }
     }
 }
@@ -344,7 +330,6 @@ impl Display for Pipe {
             Pipe::CornerTopLeft => 'J',
             Pipe::CornerBottomRight => 'F',
             Pipe::CornerBottomLeft => '7',
-            Pipe::Outside => ' ',
         };
         write!(f, "{}", c)
     }
@@ -377,4 +362,63 @@ mod tests {
         let input = read_input(10, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(435))
     }
+
+    #[test]
+    fn test_start_pipe() {
+        let map: Map = read_input(10, true, 1).unwrap().parse().unwrap();
+        assert_eq!(map.start_pipe, Pipe::CornerBottomRight);
+        assert_eq!(map.trace_loop().len(), 8);
+
+        // Junk pipes next to the start point at it as well
+        let map: Map = ".....\n.F7..\n-S-7.\n.|.|.\n.L-J.".parse().unwrap();
+        assert_eq!(map.start_pipe, Pipe::CornerBottomRight);
+        assert_eq!(map.pipe_at(map.start), Pipe::CornerBottomRight);
+        assert_eq!(map.enclosed_tiles(), 1);
+
+        assert!("...\n.S-\n...".parse::<Map>().is_err());
+    }
+
+    #[test]
+    fn test_enclosed_tiles() {
+        let squeezed = "..........\n\
+                        .S------7.\n\
+                        .|F----7|.\n\
+                        .||....||.\n\
+                        .||....||.\n\
+                        .|L-7F-J|.\n\
+                        .|..||..|.\n\
+                        .L--JL--J.\n\
+                        ..........";
+        let map: Map = squeezed.parse().unwrap();
+        assert_eq!(map.enclosed_tiles(), 4);
+        let map: Map = ".....\n.S-7.\n.|.|.\n.L-J.\n.....".parse().unwrap();
+        assert_eq!(map.enclosed_tiles(), 1);
+    }
+
+    #[test]
+    fn test_render() {
+        let map: Map = "7-F7-\n.FJ|7\nSJLL7\n|F--J\nLJ.LJ".parse().unwrap();
+        let expected = [
+            "OO┌┐O", //
+            "O┌┘│O",
+            "┌┘I└┐",
+            "│┌──┘",
+            "└┘OOO",
+        ];
+        assert_eq!(map.render(false), expected.join("\n") + "\n");
+        assert_eq!(map.enclosed_tiles(), 1);
+        let ansi = map.render(true);
+        assert!(ansi.contains("\x1b[1;33m┌\x1b[0m"));
+        assert!(!ansi.contains('O'));
+
+        for (input, enclosed) in [
+            (read_input(10, true, 2).unwrap(), 10),
+            (read_input(10, false, 2).unwrap(), 435),
+        ] {
+            let map: Map = input.parse().unwrap();
+            let rendered = map.render(false);
+            assert_eq!(rendered.matches('I').count(), enclosed);
+            assert!(!rendered.contains(['S', '.', '-', '|', 'F', '7', 'L', 'J']));
+        }
+    }
 }
@@ -1,128 +1,265 @@
-use std::collections::HashMap;
+use rayon::prelude::*;
 use std::fmt;
 use std::{fmt::Display, str::FromStr};
-use rayon::prelude::*;
 
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
+use crate::utils::random::Rng;
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
         let spring_sets: Vec<SpringSet> = input.lines().map(|line| line.parse().unwrap()).collect();
-        Some(spring_sets.par_iter().map(|s| s.count_possibles()).sum())
+        Some(
+            spring_sets
+                .par_iter()
+                .map_init(Counter::default, |counter, s| counter.count(s))
+                .sum(),
+        )
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
         let spring_sets: Vec<SpringSet> = input.lines().map(|line| line.parse().unwrap()).collect();
         let unfolded: Vec<SpringSet> = spring_sets.par_iter().map(|s| s.unfold()).collect();
-        Some(unfolded.par_iter().map(|s| s.count_possibles()).sum())
+        Some(
+            unfolded
+                .par_iter()
+                .map_init(Counter::default, |counter, s| counter.count(s))
+                .sum(),
+        )
     }
 }
 
-fn memo_count(
-    statuses: Vec<SpringStatus>,
-    broken_chains: Vec<usize>,
-    cache: &mut HashMap<(Vec<SpringStatus>, Vec<usize>), usize>,
-) -> usize {
-    let clone = (statuses.clone(), broken_chains.clone());
-    if let Some(count) = cache.get(&clone) {
-        return *count;
-    }
-    let res = count_ways(statuses, broken_chains, cache);
-    cache.insert(clone.clone(), res);
-    res
+/// Reusable buffers for counting arrangements, so one thread can count many
+/// rows without allocating for each of them.
+#[derive(Debug, Default)]
+pub struct Counter {
+    /// `ways[i * (groups + 1) + g]` is the number of arrangements of the
+    /// springs from position `i` on that contain exactly the groups from `g` on.
+    ways: Vec<usize>,
+    /// Number of operational springs before every position.
+    ok_before: Vec<usize>,
 }
 
-fn count_ways(
-    statuses: Vec<SpringStatus>,
-    broken_chains: Vec<usize>,
-    cache: &mut HashMap<(Vec<SpringStatus>, Vec<usize>), usize>,
-) -> usize {
-    let res = if statuses.is_empty() {
-        if broken_chains.is_empty() {
-            1
-        } else {
-            0
-        }
-    } else if broken_chains.is_empty() {
-        if statuses.iter().any(|s| *s == SpringStatus::Broken) {
-            0
-        } else {
-            1
+impl Counter {
+    pub fn count(&mut self, set: &SpringSet) -> usize {
+        self.fill(set);
+        self.ways[0]
+    }
+
+    fn fill(&mut self, set: &SpringSet) {
+        let (statuses, groups) = (&set.statuses, &set.broken_chains);
+        let (len, width) = (statuses.len(), groups.len() + 1);
+        self.ok_before.clear();
+        self.ok_before.push(0);
+        for status in statuses {
+            let ok = self.ok_before.last().unwrap() + usize::from(*status == SpringStatus::Ok);
+            self.ok_before.push(ok);
         }
-    } else if statuses.len() < broken_chains.len() + broken_chains.iter().sum::<usize>() - 1 {
-        0
-    } else {
-        match statuses[0] {
-            SpringStatus::Ok => memo_count(statuses[1..].to_vec(), broken_chains, cache),
-            SpringStatus::Broken => {
-                let (run, remaining) = broken_chains.split_first().unwrap();
-                let elems: Vec<_> = statuses.iter().take(*run).collect();
-                if elems.len() < *run
-                    || (*run < statuses.len() && statuses[*run] == SpringStatus::Broken)
-                    || elems.iter().any(|s| **s == SpringStatus::Ok)
-                {
-                    0
-                } else if run < &statuses.len() {
-                    let next_statuses = &statuses[run + 1..];
-                    memo_count(next_statuses.to_vec(), remaining.to_vec(), cache)
-                } else {
-                    1
+
+        self.ways.clear();
+        self.ways.resize((len + 1) * width, 0);
+        self.ways[len * width + groups.len()] = 1;
+        for i in (0..len).rev() {
+            for g in 0..width {
+                let mut ways = 0;
+                if statuses[i] != SpringStatus::Broken {
+                    ways += self.ways[(i + 1) * width + g];
+                }
+                if let Some(next) = self.after_group(set, i, g) {
+                    ways += self.ways[next * width + g + 1];
                 }
+                self.ways[i * width + g] = ways;
+            }
+        }
+    }
+
+    /// Arrangements of the springs from `i` on with the groups from `g` on,
+    /// as computed by the last [`Counter::fill`] for `set`.
+    fn ways(&self, set: &SpringSet, i: usize, g: usize) -> usize {
+        self.ways[i * (set.broken_chains.len() + 1) + g]
+    }
+
+    /// Position after group `g` and the gap following it, if the group can
+    /// start at position `i`.
+    fn after_group(&self, set: &SpringSet, i: usize, g: usize) -> Option<usize> {
+        let statuses = &set.statuses;
+        if statuses[i] == SpringStatus::Ok || g >= set.broken_chains.len() {
+            return None;
+        }
+        let end = i + set.broken_chains[g];
+        let fits = end <= statuses.len()
+            && self.ok_before[end] == self.ok_before[i]
+            && statuses.get(end) != Some(&SpringStatus::Broken);
+        fits.then_some((end + 1).min(statuses.len()))
+    }
+
+    /// Continuations at position `i` with group `g`: the position and group
+    /// after leaving spring `i` operational and after starting group `g` there,
+    /// each if it leads to any arrangement.
+    fn choices(&self, set: &SpringSet, i: usize, g: usize) -> [Option<(usize, usize)>; 2] {
+        let ok = (set.statuses[i] != SpringStatus::Broken && self.ways(set, i + 1, g) > 0)
+            .then_some((i + 1, g));
+        let group = self
+            .after_group(set, i, g)
+            .filter(|next| self.ways(set, *next, g + 1) > 0)
+            .map(|next| (next, g + 1));
+        [ok, group]
+    }
+}
+
+/// Lazy iterator over the concrete arrangements of a [`SpringSet`], in
+/// lexicographic order with operational before broken springs.
+pub struct Arrangements<'a> {
+    set: &'a SpringSet,
+    counter: Counter,
+    /// Partial arrangements with the position and group to continue from.
+    stack: Vec<(usize, usize, Vec<SpringStatus>)>,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<SpringStatus>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((i, g, mut arrangement)) = self.stack.pop() {
+            if i == self.set.statuses.len() {
+                return Some(arrangement);
             }
-            SpringStatus::Unknown => {
-                let statuses_ok: Vec<SpringStatus> = [SpringStatus::Ok]
-                    .into_iter()
-                    .chain(statuses.clone().iter().skip(1).cloned())
-                    .collect();
-                let statuses_broken: Vec<SpringStatus> = [SpringStatus::Broken]
-                    .into_iter()
-                    .chain(statuses.clone().iter().skip(1).cloned())
-                    .collect();
-                memo_count(statuses_ok.to_vec(), broken_chains.clone(), cache)
-                    + memo_count(statuses_broken.to_vec(), broken_chains.clone(), cache)
+            let [ok, group] = self.counter.choices(self.set, i, g);
+            if let Some((next, next_group)) = group {
+                let mut grouped = arrangement.clone();
+                grouped.resize(i + self.set.broken_chains[g], SpringStatus::Broken);
+                grouped.resize(next, SpringStatus::Ok);
+                self.stack.push((next, next_group, grouped));
+            }
+            if let Some((next, next_group)) = ok {
+                arrangement.push(SpringStatus::Ok);
+                self.stack.push((next, next_group, arrangement));
             }
         }
-    };
-    res
+        None
+    }
+}
+
+/// Lengths of the runs of broken springs.
+fn groups(statuses: &[SpringStatus]) -> Vec<usize> {
+    statuses
+        .split(|status| *status != SpringStatus::Broken)
+        .map(|run| run.len())
+        .filter(|len| *len > 0)
+        .collect()
 }
 
 #[derive(Debug, Clone)]
-struct SpringSet {
+pub struct SpringSet {
     statuses: Vec<SpringStatus>,
     broken_chains: Vec<usize>,
 }
 
 impl SpringSet {
     pub fn unfold(&self) -> Self {
+        self.unfold_with(5, SpringStatus::Unknown)
+    }
+
+    /// `factor` copies of the springs joined by `separator`, with the groups
+    /// repeated as often.
+    pub fn unfold_with(&self, factor: usize, separator: SpringStatus) -> Self {
         Self {
-            statuses: (0..5)
+            statuses: (0..factor)
                 .map(|_| self.statuses.clone())
                 .collect::<Vec<_>>()
-                .join(&SpringStatus::Unknown),
-            broken_chains: (0..5)
+                .join(&separator),
+            broken_chains: (0..factor)
                 .flat_map(|_| self.broken_chains.clone())
                 .collect::<Vec<_>>(),
         }
     }
 
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        let mut counter = Counter::default();
+        counter.fill(self);
+        let stack = if counter.ways[0] > 0 {
+            vec![(0, 0, vec![])]
+        } else {
+            vec![]
+        };
+        Arrangements {
+            set: self,
+            counter,
+            stack,
+        }
+    }
+
+    /// Picks one of the arrangements uniformly at random, by choosing every
+    /// branch with probability proportional to its number of arrangements.
+    pub fn sample(&self, rng: &mut Rng) -> Option<Vec<SpringStatus>> {
+        let mut counter = Counter::default();
+        counter.fill(self);
+        let (mut i, mut g) = (0, 0);
+        let mut arrangement = vec![];
+        if counter.ways[0] == 0 {
+            return None;
+        }
+        while i < self.statuses.len() {
+            let [ok, group] = counter.choices(self, i, g);
+            let ok_ways = ok.map_or(0, |(next, g)| counter.ways(self, next, g));
+            let total = counter.ways(self, i, g);
+            match group {
+                Some((next, next_group)) if rng.below(total as u64) >= ok_ways as u64 => {
+                    arrangement.resize(i + self.broken_chains[g], SpringStatus::Broken);
+                    arrangement.resize(next, SpringStatus::Ok);
+                    (i, g) = (next, next_group);
+                }
+                _ => {
+                    arrangement.push(SpringStatus::Ok);
+                    i += 1;
+                }
+            }
+        }
+        Some(arrangement)
+    }
+
     pub fn count_possibles(&self) -> usize {
-        memo_count(
-            self.statuses.clone(),
-            self.broken_chains.clone(),
-            &mut HashMap::new(),
-        )
+        Counter::default().count(self)
+    }
+
+    /// Counts by trying every assignment of the unknown springs. Only feasible
+    /// for a few unknowns, as a reference for [`Counter::count`].
+    pub fn brute_force_count(&self) -> usize {
+        let unknowns = (0..self.statuses.len())
+            .filter(|i| self.statuses[*i] == SpringStatus::Unknown)
+            .collect::<Vec<_>>();
+        let mut statuses = self.statuses.clone();
+        (0..1u64 << unknowns.len())
+            .filter(|mask| {
+                for (bit, i) in unknowns.iter().enumerate() {
+                    statuses[*i] = if mask & (1 << bit) != 0 {
+                        SpringStatus::Broken
+                    } else {
+                        SpringStatus::Ok
+                    };
+                }
+                groups(&statuses) == self.broken_chains
+            })
+            .count()
     }
 }
 
 impl FromStr for SpringSet {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (stats, chains) = s.split_once(' ').unwrap();
+        let mut cursor = Cursor::new(s);
+        let statuses = cursor
+            .take_while(|c| !c.is_whitespace())
+            .chars()
+            .map(|c| c.into())
+            .collect();
+        let broken_chains = cursor.sep_by(",", Cursor::uint)?;
+        cursor.end()?;
         Ok(Self {
-            statuses: stats.chars().map(|c| c.into()).collect(),
-            broken_chains: chains.split(',').map(|s| s.parse().unwrap()).collect(),
+            statuses,
+            broken_chains,
         })
     }
 }
@@ -140,8 +277,8 @@ impl Display for SpringSet {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
-enum SpringStatus {
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SpringStatus {
     Ok,
     Broken,
     Unknown,
@@ -198,6 +335,7 @@ impl PartialOrd for SpringStatus {
 mod tests {
     use super::*;
     use crate::utils::read_input;
+    use std::collections::{HashMap, HashSet};
 
     #[test]
     fn test_part1_example() {
@@ -222,4 +360,105 @@ mod tests {
         let input = read_input(12, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(17485169859432))
     }
+
+    #[test]
+    fn test_against_brute_force() {
+        let mut rng = Rng::new(12);
+        let mut counter = Counter::default();
+        for _ in 0..500 {
+            let row = (0..1 + rng.below(14))
+                .map(|_| ['.', '#', '?'][rng.below(3) as usize])
+                .collect::<String>();
+            let groups = (0..1 + rng.below(4))
+                .map(|_| (rng.below(4) + 1).to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            let set: SpringSet = format!("{row} {groups}").parse().unwrap();
+            assert_eq!(
+                counter.count(&set),
+                set.brute_force_count(),
+                "{row} {groups}"
+            );
+        }
+
+        for line in read_input(12, true, 1).unwrap().lines() {
+            let set: SpringSet = line.parse().unwrap();
+            assert_eq!(set.count_possibles(), set.brute_force_count(), "{line}");
+        }
+    }
+
+    fn show(arrangement: &[SpringStatus]) -> String {
+        arrangement.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let set: SpringSet = "???.### 1,1,3".parse().unwrap();
+        let all = set.arrangements().map(|a| show(&a)).collect::<Vec<_>>();
+        assert_eq!(all, vec!["#.#.###"]);
+
+        let set: SpringSet = ".??..??...?##. 1,1,3".parse().unwrap();
+        let all = set.arrangements().map(|a| show(&a)).collect::<Vec<_>>();
+        assert_eq!(
+            all,
+            vec![
+                "..#...#...###.",
+                "..#..#....###.",
+                ".#....#...###.",
+                ".#...#....###.",
+            ]
+        );
+
+        for line in read_input(12, true, 1).unwrap().lines() {
+            let set: SpringSet = line.parse().unwrap();
+            let all = set.arrangements().collect::<Vec<_>>();
+            assert_eq!(all.len(), set.count_possibles());
+            assert_eq!(all.iter().collect::<HashSet<_>>().len(), all.len());
+            for arrangement in all {
+                assert_eq!(groups(&arrangement), set.broken_chains);
+                for (concrete, original) in arrangement.iter().zip(&set.statuses) {
+                    assert!(*original == SpringStatus::Unknown || original == concrete);
+                }
+            }
+        }
+        let set: SpringSet = "#.? 2".parse().unwrap();
+        assert_eq!(set.arrangements().next(), None);
+        assert_eq!(set.sample(&mut Rng::new(1)), None);
+    }
+
+    #[test]
+    fn test_sample() {
+        let set: SpringSet = "?###???????? 3,2,1".parse().unwrap();
+        let mut rng = Rng::new(3);
+        let mut seen = HashMap::new();
+        for _ in 0..5000 {
+            let arrangement = set.sample(&mut rng).unwrap();
+            assert_eq!(groups(&arrangement), set.broken_chains);
+            *seen.entry(show(&arrangement)).or_insert(0) += 1;
+        }
+        assert_eq!(seen.len(), 10);
+        assert!(seen.values().all(|count| (400..600).contains(count)));
+    }
+
+    #[test]
+    fn test_unfold_with() {
+        let set: SpringSet = ".??..??...?##. 1,1,3".parse().unwrap();
+        assert_eq!(
+            set.unfold_with(1, SpringStatus::Unknown).to_string(),
+            set.to_string()
+        );
+        assert_eq!(
+            set.unfold_with(5, SpringStatus::Unknown).count_possibles(),
+            16384
+        );
+        assert_eq!(set.unfold_with(3, SpringStatus::Ok).count_possibles(), 64);
+        assert_eq!(
+            set.unfold_with(2, SpringStatus::Broken).to_string(),
+            ".??..??...?##.#.??..??...?##. - 113113"
+        );
+        assert_eq!(
+            set.unfold_with(0, SpringStatus::Unknown).count_possibles(),
+            1
+        );
+    }
 }
@@ -1,144 +1,147 @@
 use std::{fmt::Display, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::parse::{blocks, grid, offset_in, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let maps: Vec<Map> = input
-            .split("\n\n")
-            .map(|block| block.parse().unwrap())
-            .collect();
-        Some(maps.iter().map(|m| m.get_summary()).sum())
+        let maps = parse_maps(input).unwrap();
+        maps.iter()
+            .map(|m| m.reflection(0).map(|r| r.summary()))
+            .sum()
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
-        let maps: Vec<Map> = input
-            .split("\n\n")
-            .map(|block| block.parse().unwrap())
-            .collect();
-        Some(
-            maps.iter()
-                .map(|m| {
-                    let unsmudged = m.unsmudge();
-                    unsmudged.get_summary()
-                })
-                .sum(),
-        )
+        let maps = parse_maps(input).unwrap();
+        maps.iter()
+            .map(|m| m.reflection(1).map(|r| r.summary()))
+            .sum()
     }
 }
 
-#[derive(Debug)]
-struct Map {
-    cells: Vec<Vec<Cell>>,
-    ignore_row_mirror: Option<usize>,
-    ignore_col_mirror: Option<usize>,
+/// Parses all blank line separated maps, reporting errors relative to `input`.
+fn parse_maps(input: &str) -> Result<Vec<Map>, ParseError> {
+    blocks(input)
+        .map(|block| {
+            block
+                .parse()
+                .map_err(|e: ParseError| e.shifted(offset_in(input, block)))
+        })
+        .collect()
 }
 
-impl Map {
-    fn test_row_mirror(&self, start: usize) -> bool {
-        let max_range = start.min(self.cells.len() - 1 - 1 - start);
-        for i in 1..=max_range {
-            if self.cells[start - i] != self.cells[start + 1 + i] {
-                return false;
-            }
-        }
-        true
-    }
-
-    pub fn rows_above_mirror(&self) -> usize {
-        for (index, row) in self.cells.iter().enumerate() {
-            if Some(index + 1) != self.ignore_row_mirror
-                && self.cells.len() > index + 1
-                && *row == self.cells[index + 1]
-                && self.test_row_mirror(index)
-            {
-                return index + 1;
-            }
-        }
-
-        0
-    }
+/// Orientation of a mirror line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Between two rows.
+    Horizontal,
+    /// Between two columns.
+    Vertical,
+}
 
-    fn get_col(&self, i: usize) -> Option<Vec<Cell>> {
-        if self.cells.is_empty() || self.cells[0].len() <= i {
-            return None;
-        }
-        Some(self.cells.iter().map(|row| row[i]).collect())
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+    /// Number of rows above or columns left of the mirror line.
+    pub position: usize,
+    /// Cells as `(row, col)` that differ from their mirror image, paired with
+    /// that image. The first cell is the one above or left of the line.
+    pub differences: Vec<((usize, usize), (usize, usize))>,
+}
 
-    fn test_col_mirror(&self, start: usize) -> bool {
-        if self.cells.is_empty() {
-            return true;
-        }
-        let max_range = start.min(self.cells[0].len() - 1 - 1 - start);
-        for i in 1..=max_range {
-            if self.get_col(start - i) != self.get_col(start + 1 + i) {
-                return false;
-            }
+impl Reflection {
+    pub fn summary(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => 100 * self.position,
+            Axis::Vertical => self.position,
         }
-        true
     }
+}
 
-    pub fn cols_left_of_mirror(&self) -> usize {
-        if self.cells.is_empty() {
-            return 0;
-        }
-        for index in 0..self.cells[0].len() {
-            if Some(index + 1) != self.ignore_col_mirror
-                && self.get_col(index) == self.get_col(index + 1)
-                && self.test_col_mirror(index)
-            {
-                return index + 1;
-            }
-        }
-
-        0
-    }
+#[derive(Debug)]
+struct Map {
+    cells: Vec<Vec<Cell>>,
+    /// Rocks of every row as a bitmask over the columns.
+    rows: Vec<u64>,
+    /// Rocks of every column as a bitmask over the rows.
+    cols: Vec<u64>,
+}
 
-    pub fn get_summary(&self) -> usize {
-        self.rows_above_mirror() * 100 + self.cols_left_of_mirror()
+impl Map {
+    /// First mirror line, horizontal ones first, that reflects the pattern
+    /// with exactly `smudges` cells differing.
+    pub fn reflection(&self, smudges: usize) -> Option<Reflection> {
+        self.reflections(smudges).into_iter().next()
     }
 
-    pub fn unsmudge(&self) -> Self {
-        let old_row_mirror = self.rows_above_mirror();
-        let old_col_mirror = self.cols_left_of_mirror();
-        for (row, line) in self.cells.iter().enumerate() {
-            for col in 0..line.len() {
-                let mut candidate = Self {
-                    cells: self.cells.clone(),
-                    ignore_row_mirror: Some(old_row_mirror),
-                    ignore_col_mirror: Some(old_col_mirror),
-                };
-                candidate.flip_cell(row, col);
-                if candidate.get_summary() != 0 {
-                    return candidate;
+    /// All mirror lines that reflect the pattern with exactly `smudges`
+    /// cells differing.
+    pub fn reflections(&self, smudges: usize) -> Vec<Reflection> {
+        let mut reflections = vec![];
+        for (axis, lines) in [(Axis::Horizontal, &self.rows), (Axis::Vertical, &self.cols)] {
+            for position in 1..lines.len() {
+                let pairs = (0..position.min(lines.len() - position))
+                    .map(|i| (position - 1 - i, position + i));
+                let mut count = 0;
+                for (a, b) in pairs.clone() {
+                    count += (lines[a] ^ lines[b]).count_ones() as usize;
+                    if count > smudges {
+                        break;
+                    }
                 }
+                if count != smudges {
+                    continue;
+                }
+                let differences = pairs
+                    .flat_map(|(a, b)| {
+                        let diff = lines[a] ^ lines[b];
+                        (0..64)
+                            .filter(move |bit| diff & (1 << bit) != 0)
+                            .map(move |bit| match axis {
+                                Axis::Horizontal => ((a, bit), (b, bit)),
+                                Axis::Vertical => ((bit, a), (bit, b)),
+                            })
+                    })
+                    .collect();
+                reflections.push(Reflection {
+                    axis,
+                    position,
+                    differences,
+                });
             }
         }
-        unreachable!("No smudge found!")
-    }
-
-    fn flip_cell(&mut self, row: usize, col: usize) {
-        self.cells[row][col].flip()
+        reflections
     }
 }
 
 impl FromStr for Map {
-    type Err = ();
+    type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            cells: s
-                .lines()
-                .map(|line| line.chars().map(|c| c.into()).collect())
-                .collect(),
-            ignore_row_mirror: None,
-            ignore_col_mirror: None,
-        })
+        let cells: Vec<Vec<Cell>> = grid(s)?;
+        if cells.len() > 64 || cells[0].len() > 64 {
+            return Err(ParseError::new(
+                0,
+                "patterns can have at most 64 rows and columns",
+            ));
+        }
+        let rows = cells.iter().map(|row| mask(row.iter().copied())).collect();
+        let cols = (0..cells[0].len())
+            .map(|col| mask(cells.iter().map(|row| row[col])))
+            .collect();
+        Ok(Self { cells, rows, cols })
     }
 }
 
+/// Bitmask with bit `i` set if the `i`-th cell is a rock.
+fn mask(cells: impl Iterator<Item = Cell>) -> u64 {
+    cells
+        .enumerate()
+        .filter(|(_, cell)| *cell == Cell::Rock)
+        .fold(0, |mask, (i, _)| mask | 1 << i)
+}
+
 impl Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for row in &self.cells {
@@ -157,15 +160,6 @@ enum Cell {
     Rock,
 }
 
-impl Cell {
-    pub fn flip(&mut self) {
-        *self = match self {
-            Self::Ash => Self::Rock,
-            Self::Rock => Self::Ash,
-        }
-    }
-}
-
 impl From<char> for Cell {
     fn from(value: char) -> Self {
         match value {
@@ -217,4 +211,50 @@ mod tests {
         let input = read_input(13, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(33106))
     }
+
+    #[test]
+    fn test_reflections() {
+        let input = read_input(13, true, 1).unwrap();
+        let maps = parse_maps(&input).unwrap();
+
+        let clean = maps[0].reflection(0).unwrap();
+        assert_eq!((clean.axis, clean.position), (Axis::Vertical, 5));
+        assert!(clean.differences.is_empty());
+        let smudged = maps[0].reflection(1).unwrap();
+        assert_eq!((smudged.axis, smudged.position), (Axis::Horizontal, 3));
+        assert_eq!(smudged.differences, vec![((0, 0), (5, 0))]);
+
+        let smudged = maps[1].reflection(1).unwrap();
+        assert_eq!((smudged.axis, smudged.position), (Axis::Horizontal, 1));
+        assert_eq!(smudged.differences, vec![((0, 4), (1, 4))]);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(parse_maps("#.\n.#\n\n\n##\n#.\n").unwrap().len(), 2);
+        let err = parse_maps("#.\n.#\n\n##\n#").unwrap_err();
+        assert_eq!(err.offset, 10);
+    }
+
+    #[test]
+    fn test_several_smudges() {
+        let map: Map = "#.##\n..#.\n##..".parse().unwrap();
+        // Every candidate line with its number of differing cells
+        for (smudges, expected) in [
+            (0, vec![]),
+            (1, vec![(Axis::Vertical, 1), (Axis::Vertical, 3)]),
+            (2, vec![(Axis::Horizontal, 1)]),
+            (3, vec![(Axis::Horizontal, 2)]),
+            (4, vec![(Axis::Vertical, 2)]),
+        ] {
+            let found = map
+                .reflections(smudges)
+                .iter()
+                .map(|r| (r.axis, r.position))
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "{smudges} smudges");
+        }
+        let two = map.reflection(2).unwrap();
+        assert_eq!(two.differences, vec![((0, 0), (1, 0)), ((0, 3), (1, 3))]);
+    }
 }
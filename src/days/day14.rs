@@ -1,217 +1,208 @@
 use std::{
-    collections::HashMap,
     fmt::{Display, Formatter},
     str::FromStr,
 };
 
 use crate::solution::Solution;
+use crate::utils::cycle::{fingerprint, iterate_hashed};
+use crate::utils::parse::{grid, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let mut cols: Vec<Vec<Rock>> = vec![];
-        for line in input.lines() {
-            for (i, c) in line.chars().enumerate() {
-                if i >= cols.len() {
-                    cols.push(vec![]);
-                }
-                cols[i].push(c.into());
-            }
-        }
-        let mut total_sum = 0;
-        let total_rows = cols[0].len();
-        for col in cols {
-            let enumed = col.iter().enumerate().collect::<Vec<_>>();
-            let segments = enumed.split(|r| r.1 == &Rock::Cube);
-            let mut col_sum = 0;
-            for segment in segments {
-                if segment.is_empty() {
-                    continue;
-                }
-                let start_row = segment[0].0;
-                let start_weight = total_rows - start_row;
-                for (i, _) in segment.iter().filter(|r| r.1 == &Rock::Round).enumerate() {
-                    col_sum += start_weight - i;
-                }
-            }
-            total_sum += col_sum;
-        }
-        Some(total_sum)
+        let mut dish = input.parse::<Dish>().unwrap();
+        dish.tilt(Direction::North);
+        Some(dish.load(Direction::North))
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
-        let mut dish = input.parse::<Dish>().unwrap();
-        let mut cache = HashMap::new();
-        for i in 0..1_000_000_000 {
-            dish.cycle();
-            if let Some(j) = cache.get(&dish) {
-                let cycle_length = i - j;
-                let remaining = 1_000_000_000 - i - 1;
-                let remaining_cycles = remaining % cycle_length;
-                for _ in 0..remaining_cycles {
-                    dish.cycle();
-                }
-                break;
-            }
-            cache.insert(dish.clone(), i);
-        }
-        Some(dish.north_weight())
+        let dish = input.parse::<Dish>().unwrap();
+        let sequence = parse_sequence(SPIN_CYCLE).unwrap();
+        Some(dish.spin(&sequence, 1_000_000_000).load(Direction::North))
     }
 }
 
-enum Direction {
+/// Tilts of one spin cycle.
+const SPIN_CYCLE: &str = "NWSE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     North,
     West,
     South,
     East,
 }
 
+impl Direction {
+    /// Clockwise quarter turns of the dish after which this direction points west.
+    fn turns_to_west(self) -> usize {
+        match self {
+            Direction::West => 0,
+            Direction::South => 1,
+            Direction::East => 2,
+            Direction::North => 3,
+        }
+    }
+}
+
+/// Tilt sequence like `NWSE`, one letter per tilt.
+pub fn parse_sequence(s: &str) -> Result<Vec<Direction>, ParseError> {
+    s.trim()
+        .char_indices()
+        .map(|(offset, c)| match c {
+            'N' => Ok(Direction::North),
+            'W' => Ok(Direction::West),
+            'S' => Ok(Direction::South),
+            'E' => Ok(Direction::East),
+            _ => Err(ParseError::new(offset, "expected one of N, W, S and E")),
+        })
+        .collect()
+}
+
+/// Rocks as one bitmask per row, with bit `i` for column `i`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Dish {
-    rocks: Vec<Vec<Rock>>,
+pub struct Dish {
+    width: usize,
+    round: Vec<u128>,
+    cube: Vec<u128>,
 }
 
 impl Dish {
-    fn north_weight(&self) -> usize {
-        let mut weight = 0;
-        for (i, col) in self.rocks.iter().enumerate() {
-            for rock in col {
-                if *rock == Rock::Round {
-                    weight += self.rocks.len() - i;
-                }
-            }
-        }
-        weight
+    pub fn height(&self) -> usize {
+        self.round.len()
     }
 
-    fn cycle(&mut self) {
-        self.tilt(Direction::North);
-        self.tilt(Direction::West);
-        self.tilt(Direction::South);
-        self.tilt(Direction::East);
+    pub fn width(&self) -> usize {
+        self.width
     }
 
-    fn tilt(&mut self, direction: Direction) {
-        match direction {
-            Direction::North => {
-                for y in 0..self.rocks[0].len() {
-                    let col = self
-                        .rocks
-                        .iter()
-                        .map(|c| c[y])
-                        .enumerate()
-                        .collect::<Vec<_>>();
-                    let segments = col.split(|r| r.1 == Rock::Cube).map(|s| {
-                        if s.is_empty() {
-                            return (0, 0, 0);
-                        }
-                        let start_row = s[0].0;
-                        let count = s.iter().filter(|r| r.1 == Rock::Round).count();
-                        (start_row, count, s.len() - count)
-                    });
-                    for segment in segments {
-                        for i in 0..segment.1 {
-                            self.rocks[segment.0 + i][y] = Rock::Round;
-                        }
-                        for i in 0..segment.2 {
-                            self.rocks[segment.0 + segment.1 + i][y] = Rock::Empty;
-                        }
-                    }
-                }
-            }
-            Direction::East => {
-                for (y, row) in self.rocks.clone().iter().enumerate() {
-                    let r = row.iter().enumerate().collect::<Vec<_>>();
-                    let segments = r.split(|r| *r.1 == Rock::Cube).map(|s| {
-                        if s.is_empty() {
-                            return (0, 0, 0);
-                        }
-                        let start_col = s[0].0;
-                        let count = s.iter().filter(|r| *r.1 == Rock::Round).count();
-                        (start_col, count, s.len() - count)
-                    });
-                    for segment in segments {
-                        for i in 0..segment.2 {
-                            self.rocks[y][segment.0 + i] = Rock::Empty;
-                        }
-                        for i in 0..segment.1 {
-                            self.rocks[y][segment.0 + segment.2 + i] = Rock::Round;
-                        }
-                    }
-                }
-            }
-            Direction::South => {
-                for y in 0..self.rocks[0].len() {
-                    let col = self
-                        .rocks
-                        .iter()
-                        .map(|c| c[y])
-                        .enumerate()
-                        .collect::<Vec<_>>();
-                    let segments = col.split(|r| r.1 == Rock::Cube).map(|s| {
-                        if s.is_empty() {
-                            return (0, 0, 0);
-                        }
-                        let start_row = s[0].0;
-                        let count = s.iter().filter(|r| r.1 == Rock::Round).count();
-                        (start_row, count, s.len() - count)
-                    });
-                    for segment in segments {
-                        for i in 0..segment.2 {
-                            self.rocks[segment.0 + i][y] = Rock::Empty;
-                        }
-                        for i in 0..segment.1 {
-                            self.rocks[segment.0 + segment.2 + i][y] = Rock::Round;
-                        }
-                    }
-                }
+    /// Total load of the round rocks on the given side. A rock in the row or
+    /// column next to that side counts as far as the dish is long.
+    pub fn load(&self, side: Direction) -> usize {
+        let (width, height) = (self.width, self.height());
+        let bits = |row: u128| (0..width).filter(move |col| row & (1 << col) != 0);
+        self.round
+            .iter()
+            .enumerate()
+            .map(|(y, row)| match side {
+                Direction::North => (height - y) * row.count_ones() as usize,
+                Direction::South => (y + 1) * row.count_ones() as usize,
+                Direction::West => bits(*row).map(|x| width - x).sum(),
+                Direction::East => bits(*row).map(|x| x + 1).sum(),
+            })
+            .sum()
+    }
+
+    /// Rolls all round rocks as far as possible in `direction`, by turning
+    /// the dish so that it points west.
+    pub fn tilt(&mut self, direction: Direction) {
+        let turns = direction.turns_to_west();
+        for _ in 0..turns {
+            self.rotate_clockwise();
+        }
+        self.tilt_west();
+        for _ in 0..(4 - turns) % 4 {
+            self.rotate_clockwise();
+        }
+    }
+
+    /// Applies the tilts of `sequence` `times` times, skipping ahead once the
+    /// arrangement repeats.
+    pub fn spin(self, sequence: &[Direction], times: usize) -> Self {
+        let step = |dish: &mut Dish| {
+            for direction in sequence {
+                dish.tilt(*direction);
             }
-            Direction::West => {
-                for (y, row) in self.rocks.clone().iter().enumerate() {
-                    let r = row.iter().enumerate().collect::<Vec<_>>();
-                    let segments = r.split(|r| *r.1 == Rock::Cube).map(|s| {
-                        if s.is_empty() {
-                            return (0, 0, 0);
-                        }
-                        let start_col = s[0].0;
-                        let count = s.iter().filter(|r| *r.1 == Rock::Round).count();
-                        (start_col, count, s.len() - count)
-                    });
-                    for segment in segments {
-                        for i in 0..segment.1 {
-                            self.rocks[y][segment.0 + i] = Rock::Round;
-                        }
-                        for i in 0..segment.2 {
-                            self.rocks[y][segment.0 + segment.1 + i] = Rock::Empty;
-                        }
-                    }
-                }
+        };
+        iterate_hashed(self, times, step, fingerprint).state
+    }
+
+    fn tilt_west(&mut self) {
+        for (round, cube) in self.round.iter_mut().zip(&self.cube) {
+            let mut tilted = 0;
+            let mut start = 0;
+            while start < self.width {
+                // The segment runs up to the next cube rock or the edge
+                let end = (start + (cube >> start).trailing_zeros() as usize).min(self.width);
+                let segment = mask(end) & !mask(start);
+                let count = (*round & segment).count_ones();
+                tilted |= mask(count as usize) << start;
+                start = end + 1;
             }
+            *round = tilted;
         }
     }
+
+    fn rotate_clockwise(&mut self) {
+        let rotate = |rows: &[u128]| {
+            (0..self.width)
+                .map(|x| {
+                    rows.iter()
+                        .rev()
+                        .enumerate()
+                        .filter(|(_, row)| *row & (1 << x) != 0)
+                        .fold(0, |rotated, (y, _)| rotated | 1 << y)
+                })
+                .collect::<Vec<u128>>()
+        };
+        let (round, cube) = (rotate(&self.round), rotate(&self.cube));
+        self.width = self.height();
+        self.round = round;
+        self.cube = cube;
+    }
+}
+
+/// The lowest `bits` bits set.
+fn mask(bits: usize) -> u128 {
+    if bits >= 128 {
+        u128::MAX
+    } else {
+        (1 << bits) - 1
+    }
 }
 
 impl FromStr for Dish {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut rocks = vec![];
-        for line in s.lines() {
-            let mut row = vec![];
-            for c in line.chars() {
-                row.push(c.into());
-            }
-            rocks.push(row);
+        let rocks: Vec<Vec<Rock>> = grid(s)?;
+        let width = rocks[0].len();
+        if width > 128 || rocks.len() > 128 {
+            return Err(ParseError::new(
+                0,
+                "dish can be at most 128 tiles wide and high",
+            ));
         }
-        Ok(Self { rocks })
+        let bits = |kind: Rock| {
+            rocks
+                .iter()
+                .map(|row| {
+                    (0..width)
+                        .filter(|x| row[*x] == kind)
+                        .fold(0, |bits, x| bits | 1 << x)
+                })
+                .collect()
+        };
+        Ok(Self {
+            width,
+            round: bits(Rock::Round),
+            cube: bits(Rock::Cube),
+        })
     }
 }
 
 impl Display for Dish {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for row in &self.rocks {
-            for rock in row {
+        for (round, cube) in self.round.iter().zip(&self.cube) {
+            for x in 0..self.width {
+                let rock = if round & (1 << x) != 0 {
+                    Rock::Round
+                } else if cube & (1 << x) != 0 {
+                    Rock::Cube
+                } else {
+                    Rock::Empty
+                };
                 write!(f, "{}", rock)?;
             }
             writeln!(f)?;
@@ -273,4 +264,55 @@ mod tests {
         let input = read_input(14, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(103445))
     }
+
+    #[test]
+    fn test_tilt() {
+        let input = read_input(14, true, 1).unwrap();
+        let mut dish = input.parse::<Dish>().unwrap();
+        assert_eq!(dish.to_string(), input.trim_end().to_string() + "\n");
+        dish.tilt(Direction::North);
+        assert!(dish.to_string().starts_with("OOOO.#.O..\nOO..#....#\n"));
+        assert_eq!(dish.load(Direction::North), 136);
+
+        let spun = input
+            .parse::<Dish>()
+            .unwrap()
+            .spin(&parse_sequence("NWSE").unwrap(), 1);
+        let expected = [
+            ".....#....",
+            "....#...O#",
+            "...OO##...",
+            ".OO#......",
+            ".....OOO#.",
+            ".O#...O#.#",
+            "....O#....",
+            "......OOOO",
+            "#...O###..",
+            "#..OO#....",
+        ];
+        assert_eq!(spun.to_string(), expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_sequences_and_sides() {
+        let dish: Dish = "O.#\n.O.\n#.O\n..O".parse().unwrap();
+        assert_eq!((dish.width(), dish.height()), (3, 4));
+        assert_eq!(dish.load(Direction::North), 4 + 3 + 2 + 1);
+        assert_eq!(dish.load(Direction::South), 1 + 2 + 3 + 4);
+        assert_eq!(dish.load(Direction::West), 3 + 2 + 1 + 1);
+        assert_eq!(dish.load(Direction::East), 1 + 2 + 3 + 3);
+
+        let mut east = dish.clone();
+        east.tilt(Direction::East);
+        assert_eq!(east.to_string(), ".O#\n..O\n#.O\n..O\n");
+        let mut south = dish.clone();
+        south.tilt(Direction::South);
+        assert_eq!(south.to_string(), "..#\nO..\n#.O\n.OO\n");
+
+        // A custom sequence, and an empty one that changes nothing
+        let spun = dish.clone().spin(&parse_sequence("SE").unwrap(), 3);
+        assert_eq!(spun.to_string(), "..#\n..O\n#.O\n.OO\n");
+        assert_eq!(dish.clone().spin(&[], 5), dish);
+        assert!(parse_sequence("NWX").is_err());
+    }
 }
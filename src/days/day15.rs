@@ -1,4 +1,5 @@
 use crate::solution::Solution;
+use crate::utils::parse::{offset_in, Cursor, ParseError};
 
 pub struct Day;
 
@@ -9,12 +10,10 @@ impl Solution for Day {
 
     fn part2(&self, input: &str) -> Option<usize> {
         let mut boxes: Vec<LensBox> = (0..=255).map(|_| LensBox { lenses: vec![] }).collect();
-        input
-            .split(',')
-            .map(|s| s.into())
-            .for_each(|op: Operation| {
-                boxes[op.box_number()].apply(op);
-            });
+        let operations = list_operations(input).unwrap();
+        for op in operations {
+            boxes[op.box_number()].apply(op);
+        }
         Some(
             boxes
                 .iter()
@@ -71,16 +70,6 @@ struct Lens<'a> {
     focal_length: usize,
 }
 
-impl<'a> From<&'a str> for Lens<'a> {
-    fn from(s: &'a str) -> Self {
-        let (label, f_length) = s.split_once('=').unwrap();
-        Self {
-            label,
-            focal_length: f_length.parse().unwrap(),
-        }
-    }
-}
-
 #[derive(Debug, Clone)]
 enum Operation<'a> {
     Remove(&'a str),
@@ -100,17 +89,38 @@ impl<'a> Operation<'a> {
     }
 }
 
-impl<'a> From<&'a str> for Operation<'a> {
-    fn from(s: &'a str) -> Self {
-        if s.ends_with('-') {
-            Self::Remove(&s[0..s.len() - 1])
+impl<'a> TryFrom<&'a str> for Operation<'a> {
+    type Error = ParseError;
+
+    /// Parses `rn=1` or `cm-`.
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        let mut cursor = Cursor::new(s);
+        let label = cursor.word()?;
+        let op = if cursor.eat("-") {
+            Self::Remove(label)
         } else {
-            let label = s.split_once('=').unwrap().0;
-            Self::Set(label, s.into())
-        }
+            cursor.literal("=")?;
+            let focal_length = cursor.uint()?;
+            Self::Set(
+                label,
+                Lens {
+                    label,
+                    focal_length,
+                },
+            )
+        };
+        cursor.end()?;
+        Ok(op)
     }
 }
 
+fn list_operations(input: &str) -> Result<Vec<Operation<'_>>, ParseError> {
+    input
+        .split(',')
+        .map(|s| Operation::try_from(s).map_err(|err| err.shifted(offset_in(input, s))))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,4 +146,11 @@ mod tests {
         let input = read_input(15, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(245223))
     }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(list_operations("rn=1,cm-").is_ok());
+        let err = list_operations("rn=1,cm").unwrap_err();
+        assert_eq!(err.offset, 7);
+    }
 }
@@ -1,7 +1,8 @@
-use rayon::prelude::*;
 use std::{fmt::Display, str::FromStr};
 
-use crate::solution::Solution;
+use crate::solution::{Solution, VisualizeOptions};
+use crate::utils::graph::Graph;
+use crate::utils::parse::{grid, Cursor, ParseError};
 
 pub struct Day;
 
@@ -14,46 +15,97 @@ impl Solution for Day {
 
     fn part2(&self, input: &str) -> Option<usize> {
         let map = input.parse::<Map>().unwrap();
-        let row_count = map.tiles.len();
-        let col_count = map.tiles[0].len();
-        let max_l = (0..row_count)
-            .into_par_iter()
-            .map(|i| {
-                let mut map_l = map.clone();
-                map_l.calc_visits((i, 0, Direction::Right));
-                map_l.count_visited()
-            })
-            .max()
-            .unwrap();
-        let max_r = (0..row_count)
-            .into_par_iter()
-            .map(|i| {
-                let mut map_r = map.clone();
-                map_r.calc_visits((i, col_count - 1, Direction::Left));
-                map_r.count_visited()
-            })
-            .max()
-            .unwrap();
-        let max_t = (0..col_count)
-            .into_par_iter()
-            .map(|i| {
-                let mut map_t = map.clone();
-                map_t.calc_visits((0, i, Direction::Bottom));
-                map_t.count_visited()
-            })
-            .max()
-            .unwrap();
-        let max_b = (0..col_count)
-            .into_par_iter()
-            .map(|i| {
-                let mut map_b = map.clone();
-                map_b.calc_visits((row_count - 1, i, Direction::Top));
-                map_b.count_visited()
-            })
-            .max()
-            .unwrap();
+        map.entry_counts().into_iter().map(|(_, count)| count).max()
+    }
+
+    /// Beams from the entry given as `row,col,direction`, or else from the
+    /// top left corner for part 1 and the best entry for part 2. An invalid
+    /// entry is reported instead of the beams.
+    fn visualize(&self, input: &str, options: &VisualizeOptions) -> Option<String> {
+        let mut map = input.parse::<Map>().ok()?;
+        let entry = match &options.entry {
+            Some(entry) => match parse_entry(entry, &map) {
+                Ok(entry) => entry,
+                Err(err) => return Some(format!("Invalid entry {entry:?}: {err}\n")),
+            },
+            None if options.part2 => {
+                map.entry_counts()
+                    .into_iter()
+                    .max_by_key(|(_, count)| *count)?
+                    .0
+            }
+            None => (0, 0, Direction::Right),
+        };
+        map.calc_visits(entry);
+        Some(if options.image {
+            map.to_ppm(PPM_SCALE)
+        } else {
+            map.render(!options.plain)
+        })
+    }
+}
+
+/// Number of energized tiles for every beam entering the map from its border.
+pub fn entry_counts(input: &str) -> Result<Vec<(Entry, usize)>, ParseError> {
+    Ok(input.parse::<Map>()?.entry_counts())
+}
+
+/// Pixels per tile in [`Map::to_ppm`].
+const PPM_SCALE: usize = 8;
+
+/// Entry given as `row,col,direction`, which has to be on the border of `map`.
+fn parse_entry(s: &str, map: &Map) -> Result<Entry, ParseError> {
+    let mut cursor = Cursor::new(s);
+    let row = cursor.uint()?;
+    cursor.literal(",")?;
+    let col = cursor.uint()?;
+    cursor.literal(",")?;
+    let direction = cursor.token(|_| false)?;
+    cursor.end()?;
+    let (rows, cols) = (map.tiles.len(), map.tiles[0].len());
+    if row >= rows || col >= cols {
+        return Err(ParseError::new(
+            0,
+            format!("position is outside the {rows}x{cols} map"),
+        ));
+    }
+    if row != 0 && row != rows - 1 && col != 0 && col != cols - 1 {
+        return Err(ParseError::new(
+            0,
+            "position is not on the border of the map",
+        ));
+    }
+    Ok((row, col, direction))
+}
+
+/// A beam entering the tile at `(row, col)` while moving in a direction.
+pub type Entry = (usize, usize, Direction);
 
-        Some(max_l.max(max_r).max(max_t).max(max_b))
+/// Set of tiles, indexed by `row * width + col`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tiles(Vec<u64>);
+
+impl Tiles {
+    fn new(size: usize) -> Self {
+        Tiles(vec![0; size.div_ceil(64)])
+    }
+
+    fn insert(&mut self, index: usize) {
+        self.0[index / 64] |= 1 << (index % 64);
+    }
+
+    fn union_with(&mut self, other: &Tiles) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
+        }
+    }
+
+    fn union_len(&self, other: &Tiles) -> usize {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(a, b)| (a | b).count_ones() as usize)
+            .sum()
     }
 }
 
@@ -71,6 +123,129 @@ impl Map {
             .sum()
     }
 
+    /// Every beam entering the map from its border, moving away from it.
+    pub fn entries(&self) -> Vec<Entry> {
+        let (rows, cols) = (self.tiles.len(), self.tiles[0].len());
+        (0..rows)
+            .map(|row| (row, 0, Direction::Right))
+            .chain((0..rows).map(|row| (row, cols - 1, Direction::Left)))
+            .chain((0..cols).map(|col| (0, col, Direction::Bottom)))
+            .chain((0..cols).map(|col| (rows - 1, col, Direction::Top)))
+            .collect()
+    }
+
+    /// Number of energized tiles for every entry of [`Map::entries`].
+    ///
+    /// Straight runs of the beam only branch at splitters hit from the side,
+    /// and such a splitter always energizes the same tiles. So the splitters
+    /// form a graph whose edges are the runs between them, and the tiles
+    /// reachable from each splitter are collected once per strongly connected
+    /// component, in reverse topological order.
+    pub fn entry_counts(&self) -> Vec<(Entry, usize)> {
+        let entries = self.entries();
+        let runs = entries
+            .iter()
+            .map(|entry| self.run(*entry))
+            .collect::<Vec<_>>();
+
+        let mut graph: Graph<usize> = Graph::directed();
+        let mut own = vec![];
+        let mut edges = vec![];
+        let mut queue = runs.iter().filter_map(|(_, hit)| *hit).collect::<Vec<_>>();
+        while let Some(splitter) = queue.pop() {
+            if graph.id(&splitter).is_some() {
+                continue;
+            }
+            graph.add_node(splitter);
+            let mut tiles = Tiles::new(self.size());
+            tiles.insert(splitter);
+            let (row, col) = (
+                splitter / self.tiles[0].len(),
+                splitter % self.tiles[0].len(),
+            );
+            for direction in self.tiles[row][col].split_directions() {
+                let Some(next) = self.step(row, col, direction) else {
+                    continue;
+                };
+                let (run, hit) = self.run(next);
+                tiles.union_with(&run);
+                if let Some(hit) = hit {
+                    edges.push((splitter, hit));
+                    queue.push(hit);
+                }
+            }
+            own.push(tiles);
+        }
+        for (from, to) in edges {
+            graph.add_edge(from, to, ());
+        }
+
+        let mut reach: Vec<Option<Tiles>> = vec![None; graph.len()];
+        for component in graph.strongly_connected_components() {
+            let mut tiles = Tiles::new(self.size());
+            for node in &component {
+                tiles.union_with(&own[*node]);
+                for (next, _) in graph.neighbors(*node) {
+                    if let Some(next) = &reach[next] {
+                        tiles.union_with(next);
+                    }
+                }
+            }
+            for node in component {
+                reach[node] = Some(tiles.clone());
+            }
+        }
+
+        entries
+            .into_iter()
+            .zip(runs)
+            .map(|(entry, (run, hit))| {
+                let count = match hit.and_then(|hit| reach[graph.id(&hit)?].as_ref()) {
+                    Some(reached) => run.union_len(reached),
+                    None => run.union_len(&run),
+                };
+                (entry, count)
+            })
+            .collect()
+    }
+
+    fn size(&self) -> usize {
+        self.tiles.len() * self.tiles[0].len()
+    }
+
+    /// The tile next to `(row, col)` in `direction`, if it is on the map.
+    fn step(&self, row: usize, col: usize, direction: Direction) -> Option<Entry> {
+        let (row, col) = match direction {
+            Direction::Top => (row.checked_sub(1)?, col),
+            Direction::Right => (row, col + 1),
+            Direction::Bottom => (row + 1, col),
+            Direction::Left => (row, col.checked_sub(1)?),
+        };
+        (row < self.tiles.len() && col < self.tiles[0].len()).then_some((row, col, direction))
+    }
+
+    /// Follows a beam until it leaves the map or hits a splitter from the
+    /// side. Returns the energized tiles and the index of that splitter.
+    fn run(&self, entry: Entry) -> (Tiles, Option<usize>) {
+        let width = self.tiles[0].len();
+        let mut tiles = Tiles::new(self.size());
+        let mut state = Some(entry);
+        // Without splitters, a beam can only loop back through the splitter
+        // it came from, so it has to repeat after visiting every state once.
+        for _ in 0..=4 * self.size() {
+            let Some((row, col, direction)) = state else {
+                break;
+            };
+            tiles.insert(row * width + col);
+            let directions = direction.mirror(&self.tiles[row][col]);
+            if directions.len() > 1 {
+                return (tiles, Some(row * width + col));
+            }
+            state = self.step(row, col, directions[0]);
+        }
+        (tiles, None)
+    }
+
     pub fn calc_visits(&mut self, start: (usize, usize, Direction)) {
         let mut stack = vec![start];
         while let Some((row, col, dir)) = stack.pop() {
@@ -101,17 +276,10 @@ impl Map {
 }
 
 impl FromStr for Map {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut tiles = vec![];
-        for line in s.lines() {
-            let mut row = vec![];
-            for c in line.chars() {
-                row.push(c.into());
-            }
-            tiles.push(row);
-        }
+        let tiles: Vec<Vec<Tile>> = grid(s)?;
         Ok(Map {
             visited: vec![vec![[false; 4]; tiles[0].len()]; tiles.len()],
             tiles,
@@ -119,22 +287,74 @@ impl FromStr for Map {
     }
 }
 
+impl Map {
+    /// The map with the beams drawn over empty tiles, as an arrow where one
+    /// beam passed and as the number of directions where several did. With
+    /// `ansi`, energized tiles are highlighted.
+    pub fn render(&self, ansi: bool) -> String {
+        let mut rendered = String::new();
+        for (tiles, visited) in self.tiles.iter().zip(&self.visited) {
+            for (tile, visited) in tiles.iter().zip(visited) {
+                let directions = visited.iter().filter(|b| **b).count();
+                let symbol = match (tile, directions) {
+                    (Tile::Empty, 0) => '.',
+                    (Tile::Empty, 1) => {
+                        Direction::ALL[visited.iter().position(|b| *b).unwrap()].arrow()
+                    }
+                    (Tile::Empty, n) => char::from_digit(n as u32, 10).unwrap(),
+                    (tile, _) => tile.symbol(),
+                };
+                if ansi && directions > 0 {
+                    rendered.push_str(&format!("\x1b[1;33m{symbol}\x1b[0m"));
+                } else {
+                    rendered.push(symbol);
+                }
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+
+    /// Plain PPM image with `scale` pixels per tile. Energized tiles get
+    /// brighter with the number of beam directions passing them.
+    pub fn to_ppm(&self, scale: usize) -> String {
+        let (rows, cols) = (self.tiles.len(), self.tiles[0].len());
+        let mut image = format!("P3\n{} {}\n255\n", cols * scale, rows * scale);
+        for (tiles, visited) in self.tiles.iter().zip(&self.visited) {
+            let pixels = tiles
+                .iter()
+                .zip(visited)
+                .map(
+                    |(tile, visited)| match (tile, visited.iter().filter(|b| **b).count()) {
+                        (Tile::Empty, 0) => (20, 20, 30),
+                        (_, 0) => (110, 110, 130),
+                        (Tile::Empty, 1) => (200, 120, 0),
+                        (Tile::Empty, 2) => (240, 180, 0),
+                        (Tile::Empty, _) => (255, 240, 80),
+                        (_, _) => (255, 255, 255),
+                    },
+                )
+                .collect::<Vec<_>>();
+            let line = pixels
+                .iter()
+                .flat_map(|(r, g, b)| std::iter::repeat_n(format!("{r} {g} {b}"), scale))
+                .collect::<Vec<_>>()
+                .join(" ");
+            for _ in 0..scale {
+                image.push_str(&line);
+                image.push('\n');
+            }
+        }
+        image
+    }
+}
+
 impl Display for Map {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Mirrors:")?;
         for row in &self.tiles {
             for tile in row {
-                write!(
-                    f,
-                    "{}",
-                    match tile {
-                        Tile::Empty => '.',
-                        Tile::MirrorL => '\\',
-                        Tile::MirrorR => '/',
-                        Tile::SplitH => '-',
-                        Tile::SplitV => '|',
-                    }
-                )?;
+                write!(f, "{}", tile.symbol())?;
             }
             writeln!(f)?;
         }
@@ -156,8 +376,8 @@ impl Display for Map {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
     Top,
     Right,
     Bottom,
@@ -165,6 +385,23 @@ enum Direction {
 }
 
 impl Direction {
+    /// All directions, in the order of [`Direction::index`].
+    const ALL: [Direction; 4] = [
+        Direction::Top,
+        Direction::Right,
+        Direction::Bottom,
+        Direction::Left,
+    ];
+
+    fn arrow(&self) -> char {
+        match self {
+            Direction::Top => '^',
+            Direction::Right => '>',
+            Direction::Bottom => 'v',
+            Direction::Left => '<',
+        }
+    }
+
     fn index(&self) -> usize {
         match self {
             Direction::Top => 0,
@@ -190,13 +427,27 @@ impl Direction {
             },
             Tile::SplitH => match self {
                 Direction::Top | Direction::Bottom => vec![Direction::Left, Direction::Right],
-                x => vec![x.clone()],
+                x => vec![*x],
             },
             Tile::SplitV => match self {
                 Direction::Left | Direction::Right => vec![Direction::Top, Direction::Bottom],
-                x => vec![x.clone()],
+                x => vec![*x],
             },
-            Tile::Empty => vec![self.clone()],
+            Tile::Empty => vec![*self],
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "^" | "up" => Ok(Direction::Top),
+            ">" | "right" => Ok(Direction::Right),
+            "v" | "down" => Ok(Direction::Bottom),
+            "<" | "left" => Ok(Direction::Left),
+            _ => Err(ParseError::new(0, "expected a direction like > or right")),
         }
     }
 }
@@ -210,6 +461,27 @@ enum Tile {
     SplitV,
 }
 
+impl Tile {
+    fn symbol(&self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::MirrorL => '\\',
+            Tile::MirrorR => '/',
+            Tile::SplitH => '-',
+            Tile::SplitV => '|',
+        }
+    }
+
+    /// Directions a splitter sends a beam in when hit from the side.
+    fn split_directions(&self) -> Vec<Direction> {
+        match self {
+            Tile::SplitH => vec![Direction::Left, Direction::Right],
+            Tile::SplitV => vec![Direction::Top, Direction::Bottom],
+            _ => vec![],
+        }
+    }
+}
+
 impl From<char> for Tile {
     fn from(c: char) -> Self {
         match c {
@@ -250,4 +522,81 @@ mod tests {
         let input = read_input(16, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(8089));
     }
+
+    #[test]
+    fn test_entry_counts() {
+        for example in [true, false] {
+            let map = read_input(16, example, 2).unwrap().parse::<Map>().unwrap();
+            let counts = map.entry_counts();
+            assert_eq!(counts.len(), 2 * (map.tiles.len() + map.tiles[0].len()));
+            for (entry, count) in counts {
+                let mut reference = map.clone();
+                reference.calc_visits(entry);
+                assert_eq!(count, reference.count_visited(), "{entry:?}");
+            }
+        }
+
+        let counts = entry_counts(&read_input(16, true, 2).unwrap()).unwrap();
+        assert!(counts.contains(&((0, 0, Direction::Right), 46)));
+        assert!(counts.contains(&((0, 3, Direction::Bottom), 51)));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut map = read_input(16, true, 1).unwrap().parse::<Map>().unwrap();
+        map.calc_visits((0, 0, Direction::Right));
+        let expected = [
+            ">|<<<\\....",
+            "|v-.\\^....",
+            ".v...|->>>",
+            ".v...v^.|.",
+            ".v...v^...",
+            ".v...v^..\\",
+            ".v../2\\\\..",
+            "<->-/vv|..",
+            ".|<<<2-|.\\",
+            ".v//.|.v..",
+        ];
+        assert_eq!(map.render(false), expected.join("\n") + "\n");
+        assert_eq!(map.render(true).matches("\x1b[1;33m").count(), 46);
+
+        let image = map.to_ppm(2);
+        assert!(image.starts_with("P3\n20 20\n255\n"));
+        assert_eq!(image.lines().count(), 3 + 20);
+
+        assert_eq!(parse_entry("0,3,v", &map), Ok((0, 3, Direction::Bottom)));
+        assert_eq!(parse_entry("2,0,right", &map), Ok((2, 0, Direction::Right)));
+        assert!(parse_entry("2,0,east", &map).is_err());
+        assert!(parse_entry("50,50,right", &map).is_err());
+        assert!(parse_entry("9,10,left", &map).is_err());
+        assert!(parse_entry("4,4,up", &map).is_err());
+
+        let options = VisualizeOptions {
+            part2: true,
+            plain: true,
+            ..Default::default()
+        };
+        let input = read_input(16, true, 2).unwrap();
+        let rendered = Day.visualize(&input, &options).unwrap();
+        assert!(rendered.starts_with(".|<2<\\...."));
+        let options = VisualizeOptions {
+            entry: Some("50,50,right".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Day.visualize(&input, &options).unwrap(),
+            "Invalid entry \"50,50,right\": at offset 0: position is outside the 10x10 map\n"
+        );
+    }
+
+    #[test]
+    fn test_loop_through_splitter() {
+        // The beam leaving the splitter to the right comes back from the left
+        let map = "/-\\.\n\\-/.".parse::<Map>().unwrap();
+        for (entry, count) in map.entry_counts() {
+            let mut reference = map.clone();
+            reference.calc_visits(entry);
+            assert_eq!(count, reference.count_visited(), "{entry:?}");
+        }
+    }
 }
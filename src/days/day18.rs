@@ -1,6 +1,8 @@
 use std::str::FromStr;
 
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
+use crate::utils::polygon::Polygon;
 
 pub struct Day;
 
@@ -14,21 +16,20 @@ impl Solution for Day {
     }
 }
 
-fn execute(input: &str, parser: fn(&str) -> Instruction) -> usize {
-    let instructions: Vec<Instruction> = input.lines().map(parser).collect();
-    // Solve using Green's theorem for polygons
-    let mut x = 0;
-    let mut perimeter = 0;
-    let mut area = 0;
-    for instruction in instructions {
-        let (dy, dx) = instruction
-            .direction
-            .get_scaled_vector(instruction.distance);
-        x += dx;
-        perimeter += instruction.distance;
-        area += x * dy;
-    }
-    (area + perimeter / 2 + 1) as usize
+fn execute(input: &str, parser: fn(&str) -> Result<Instruction, ParseError>) -> usize {
+    let instructions: Vec<Instruction> =
+        input.lines().map(parser).collect::<Result<_, _>>().unwrap();
+    // The trench is the boundary of the lagoon, its inside is given by Pick's theorem
+    let lagoon = Polygon::from_moves(
+        (0, 0),
+        instructions.iter().map(|instruction| {
+            let (dy, dx) = instruction
+                .direction
+                .get_scaled_vector(instruction.distance);
+            (dx as i64, dy as i64)
+        }),
+    );
+    (lagoon.interior_points().unwrap_or(0) + lagoon.boundary_points()) as usize
 }
 
 #[derive(Debug)]
@@ -38,24 +39,29 @@ struct Instruction {
 }
 
 impl Instruction {
-    pub fn parse_part1(input: &str) -> Self {
-        let parts = input.split_whitespace().collect::<Vec<_>>();
-        let direction = parts[0].parse().unwrap();
-        let distance = parts[1].parse().unwrap();
-        Instruction {
+    pub fn parse_part1(input: &str) -> Result<Self, ParseError> {
+        let mut cursor = Cursor::new(input);
+        let direction = cursor.token(char::is_whitespace)?;
+        let distance = cursor.uint()?;
+        Ok(Instruction {
             direction,
             distance,
-        }
+        })
     }
 
-    pub fn parse_part2(input: &str) -> Self {
-        let color = input.split_whitespace().last().unwrap();
-        let parts = color[2..color.len() - 1].to_string();
-        let (distance, direction) = parts.split_at(5);
-        Instruction {
-            direction: direction.parse().unwrap(),
-            distance: isize::from_str_radix(distance, 16).unwrap(),
-        }
+    pub fn parse_part2(input: &str) -> Result<Self, ParseError> {
+        let mut cursor = Cursor::new(input);
+        cursor.word()?;
+        cursor.uint::<isize>()?;
+        cursor.literal("(#")?;
+        let distance = cursor.hex(5)? as isize;
+        let direction = cursor.hex(1)?.to_string().parse()?;
+        cursor.literal(")")?;
+        cursor.end()?;
+        Ok(Instruction {
+            direction,
+            distance,
+        })
     }
 }
 
@@ -79,7 +85,7 @@ impl Direction {
 }
 
 impl FromStr for Direction {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
@@ -87,7 +93,7 @@ impl FromStr for Direction {
             "D" | "1" => Ok(Direction::Down),
             "L" | "2" => Ok(Direction::Left),
             "R" | "0" => Ok(Direction::Right),
-            _ => Err(()),
+            _ => Err(ParseError::new(0, format!("invalid direction {s:?}"))),
         }
     }
 }
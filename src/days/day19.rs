@@ -1,6 +1,7 @@
 use std::{collections::HashMap, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::parse::{Cursor, ParseError};
 
 pub struct Day;
 
@@ -70,14 +71,23 @@ impl Part {
 }
 
 impl FromStr for Part {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<usize> = s[1..s.len() - 1]
-            .split(',')
-            .map(|a| a.split('=').collect::<Vec<_>>()[1])
-            .map(|a| a.parse().unwrap())
-            .collect();
+        let mut cursor = Cursor::new(s);
+        cursor.literal("{")?;
+        let parts: Vec<usize> = cursor.sep_by(",", |cursor| {
+            cursor.word()?;
+            cursor.literal("=")?;
+            cursor.uint()
+        })?;
+        cursor.skip_whitespace();
+        let end_at = cursor.pos();
+        cursor.literal("}")?;
+        cursor.end()?;
+        if parts.len() != 4 {
+            return Err(ParseError::new(end_at, "expected four ratings"));
+        }
         Ok(Part {
             x: parts[0],
             m: parts[1],
@@ -133,14 +143,15 @@ impl Workflow {
 }
 
 impl FromStr for Workflow {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (name, rules) = s.split_once('{').unwrap();
-        let rules = rules[..rules.len() - 1]
-            .split(',')
-            .map(|l| l.parse().unwrap())
-            .collect();
+        let mut cursor = Cursor::new(s);
+        let name = cursor.word()?;
+        cursor.literal("{")?;
+        let rules = cursor.sep_by(",", |cursor| cursor.token(|c| c == ',' || c == '}'))?;
+        cursor.literal("}")?;
+        cursor.end()?;
         Ok(Workflow {
             name: name.to_string(),
             rules,
@@ -228,26 +239,35 @@ impl Condition {
 }
 
 impl FromStr for Condition {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.contains('<') {
-            let parts = s.split('<').collect::<Vec<_>>();
-            Ok(Condition {
-                attr: parts[0].chars().next().unwrap(),
-                operator: '<',
-                value: parts[1].parse().unwrap(),
-            })
-        } else if s.contains('>') {
-            let parts = s.split('>').collect::<Vec<_>>();
-            Ok(Condition {
-                attr: parts[0].chars().next().unwrap(),
-                operator: '>',
-                value: parts[1].parse().unwrap(),
-            })
+        let mut cursor = Cursor::new(s);
+        cursor.skip_whitespace();
+        let attr_at = cursor.pos();
+        let attr = match cursor.word()? {
+            attr @ ("x" | "m" | "a" | "s") => attr.chars().next().unwrap(),
+            attr => {
+                return Err(ParseError::new(
+                    attr_at,
+                    format!("unknown attribute {attr}"),
+                ))
+            }
+        };
+        let operator = if cursor.eat("<") {
+            '<'
+        } else if cursor.eat(">") {
+            '>'
         } else {
-            Err(())
-        }
+            return Err(cursor.error("expected '<' or '>'"));
+        };
+        let value = cursor.uint()?;
+        cursor.end()?;
+        Ok(Condition {
+            attr,
+            operator,
+            value,
+        })
     }
 }
 
@@ -268,20 +288,18 @@ impl Rule {
 }
 
 impl FromStr for Rule {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(':').collect::<Vec<_>>();
-        if parts.len() == 1 {
-            Ok(Rule {
+        match s.split_once(':') {
+            None => Ok(Rule {
                 condition: None,
-                action: parts[0].to_string(),
-            })
-        } else {
-            Ok(Rule {
-                condition: parts[0].parse().ok(),
-                action: parts[1].to_string(),
-            })
+                action: s.to_string(),
+            }),
+            Some((condition, action)) => Ok(Rule {
+                condition: Some(condition.parse()?),
+                action: action.to_string(),
+            }),
         }
     }
 }
@@ -292,6 +310,14 @@ mod tests {
     use crate::solution::Solution;
     use crate::utils::read_input;
 
+    #[test]
+    fn test_parse_errors() {
+        let err = "{x=1,m=2,a=3}".parse::<Part>().unwrap_err();
+        assert_eq!(err.offset, 12);
+        let err = "  q<5".parse::<Condition>().unwrap_err();
+        assert_eq!(err.offset, 2);
+    }
+
     #[test]
     fn test_part1_example() {
         if let Some(input) = read_input(19, true, 1) {
@@ -1,73 +1,156 @@
 use std::{
     collections::{HashMap, VecDeque},
+    fmt::{self, Display},
     str::FromStr,
 };
 
-use crate::solution::Solution;
+use crate::solution::{Solution, VisualizeOptions};
+use crate::utils::cycle::{self, fingerprint, Cycle};
+use crate::utils::graph::Graph;
+use crate::utils::math::lcm;
+use crate::utils::parse::{lines, offset_in, Cursor, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let mut modules = input
-            .lines()
-            .map(|l| l.parse().unwrap())
-            .map(|m: Module| (m.name().to_string(), m))
-            .collect::<HashMap<String, Module>>();
-        for (_, module) in modules.clone() {
-            for target in module.target_names() {
-                if modules.contains_key(&target) {
-                    modules
-                        .get_mut(&target)
-                        .expect("Could not find module")
-                        .register_input(&module);
-                }
-            }
-        }
+        let mut circuit: Circuit = input.parse().unwrap();
         let mut high_count = 0;
         let mut low_count = 0;
         for _ in 0..1000 {
-            let mut signal_queue = VecDeque::from([Signal {
-                from: "button".to_string(),
-                to: "broadcaster".to_string(),
-                pulse: Pulse::Low,
-            }]);
-            while let Some(signal) = signal_queue.pop_front() {
-                // println!("Processing {:?}", signal);
+            for signal in circuit.press() {
                 match signal.pulse {
                     Pulse::High => high_count += 1,
                     Pulse::Low => low_count += 1,
                 }
-                if modules.contains_key(&signal.to) {
-                    let module = modules.get_mut(&signal.to).unwrap();
-                    let new_signals = module.handle_signal(signal);
-                    signal_queue.extend(new_signals);
-                }
             }
         }
         Some(high_count * low_count)
     }
 
-    fn part2(&self, _input: &str) -> Option<usize> {
-        None
+    fn part2(&self, input: &str) -> Option<usize> {
+        // See `presses_until_low` for why a circuit has no answer
+        let circuit = input.parse().unwrap();
+        presses_until_low(circuit, "rx").ok()
+    }
+
+    fn visualize(&self, input: &str, options: &VisualizeOptions) -> Option<String> {
+        let mut circuit: Circuit = input.parse().unwrap();
+        for _ in 0..options.steps.unwrap_or(0) {
+            circuit.press();
+        }
+        Some(circuit.to_dot(options.steps.is_some()))
     }
 }
 
+/// Presses tried by the brute force simulation before giving up.
+const BRUTE_FORCE_LIMIT: usize = 100_000;
+
+/// Presses simulated to find the periods of the inputs of the final conjunction.
+const ANALYSIS_LIMIT: usize = 100_000;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-enum Pulse {
-    High,
-    Low,
+pub enum CircuitError {
+    /// No module sends pulses to the target.
+    Unreachable(String),
+    /// The circuit is not a conjunction over periodic inputs and brute force
+    /// did not find the pulse either.
+    UnexpectedShape(String),
+}
+
+impl Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable(target) => write!(f, "no module sends pulses to {target}"),
+            Self::UnexpectedShape(reason) => write!(f, "unexpected circuit shape: {reason}"),
+        }
+    }
 }
 
-impl Pulse {
-    pub fn flip(&mut self) {
-        *self = match self {
-            Self::High => Self::Low,
-            Self::Low => Self::High,
+impl std::error::Error for CircuitError {}
+
+/// Number of presses until `target` receives a low pulse for the first time.
+///
+/// Real inputs feed `target` from a single conjunction whose inputs each send
+/// a high pulse once every few thousand presses, so the answer is the least
+/// common multiple of those periods. Everything else is simulated directly.
+pub fn presses_until_low(mut circuit: Circuit, target: &str) -> Result<usize, CircuitError> {
+    let unreachable = || CircuitError::Unreachable(target.to_string());
+    let target_id = circuit.id(target).ok_or_else(unreachable)?;
+    match circuit.modules[target_id].inputs.as_slice() {
+        [] => Err(unreachable()),
+        [feeder] if matches!(circuit.modules[*feeder].kind, Kind::Conjunction { .. }) => {
+            let feeder = *feeder;
+            conjunction_periods(&mut circuit, target_id, feeder)
         }
+        _ => brute_force(&mut circuit, target_id).ok_or_else(|| {
+            CircuitError::UnexpectedShape(format!(
+                "{target} is not fed by a single conjunction and receives no low pulse within {BRUTE_FORCE_LIMIT} presses"
+            ))
+        }),
     }
 }
 
+fn brute_force(circuit: &mut Circuit, target: ModuleId) -> Option<usize> {
+    let watcher = circuit.watch(None, Some(target), Some(Pulse::Low));
+    while circuit.presses() < BRUTE_FORCE_LIMIT {
+        circuit.press();
+        if let Some(press) = circuit.hits(watcher).first() {
+            return Some(*press);
+        }
+    }
+    None
+}
+
+/// Simulates until every input of `conjunction` sent a high pulse twice and
+/// combines the observed periods.
+fn conjunction_periods(
+    circuit: &mut Circuit,
+    target: ModuleId,
+    conjunction: ModuleId,
+) -> Result<usize, CircuitError> {
+    let target_watcher = circuit.watch(None, Some(target), Some(Pulse::Low));
+    let inputs = circuit.modules[conjunction].inputs.clone();
+    let watchers = inputs
+        .iter()
+        .map(|input| circuit.watch(Some(*input), Some(conjunction), Some(Pulse::High)))
+        .collect::<Vec<_>>();
+    while circuit.presses() < ANALYSIS_LIMIT {
+        circuit.press();
+        if let Some(press) = circuit.hits(target_watcher).first() {
+            return Ok(*press);
+        }
+        if watchers.iter().all(|w| circuit.hits(*w).len() >= 2) {
+            break;
+        }
+    }
+
+    let mut result = 1;
+    for (input, watcher) in inputs.iter().zip(watchers) {
+        let name = circuit.name(*input);
+        match circuit.hits(watcher) {
+            [first, second, ..] if *second == 2 * first => result = lcm(result, *first),
+            [first, second, ..] => {
+                return Err(CircuitError::UnexpectedShape(format!(
+                    "{name} sends high pulses at presses {first} and {second}, which is not a cycle starting at zero"
+                )))
+            }
+            _ => {
+                return Err(CircuitError::UnexpectedShape(format!(
+                    "{name} sends no periodic high pulses within {ANALYSIS_LIMIT} presses"
+                )))
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pulse {
+    High,
+    Low,
+}
+
 impl From<bool> for Pulse {
     fn from(b: bool) -> Self {
         if b {
@@ -78,123 +161,340 @@ impl From<bool> for Pulse {
     }
 }
 
-impl From<Pulse> for bool {
-    fn from(p: Pulse) -> Self {
-        match p {
-            Pulse::High => true,
-            Pulse::Low => false,
+/// Index of a module inside its [`Circuit`].
+pub type ModuleId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signal {
+    pub from: ModuleId,
+    pub to: ModuleId,
+    pub pulse: Pulse,
+}
+
+/// Behaviour and internal state of a module.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// Sends a single low pulse to the broadcaster per press.
+    Button,
+    Broadcaster,
+    FlipFlop {
+        on: bool,
+    },
+    /// Remembers the last pulse of every input, in the order of `inputs`.
+    Conjunction {
+        memory: Vec<Pulse>,
+    },
+    /// Modules that are only mentioned as targets, like `rx`.
+    Output,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub kind: Kind,
+    pub inputs: Vec<ModuleId>,
+    pub targets: Vec<ModuleId>,
+}
+
+impl Module {
+    /// Updates the state and returns the pulse sent to all targets, if any.
+    fn receive(&mut self, from: ModuleId, pulse: Pulse) -> Option<Pulse> {
+        match &mut self.kind {
+            Kind::Button | Kind::Output => None,
+            Kind::Broadcaster => Some(pulse),
+            Kind::FlipFlop { on } => {
+                if pulse == Pulse::High {
+                    return None;
+                }
+                *on = !*on;
+                Some(Pulse::from(*on))
+            }
+            Kind::Conjunction { memory } => {
+                let input = self.inputs.iter().position(|i| *i == from)?;
+                memory[input] = pulse;
+                Some(Pulse::from(!memory.iter().all(|p| *p == Pulse::High)))
+            }
         }
     }
+
+    pub fn state_hash(&self) -> u64 {
+        fingerprint(&self.kind)
+    }
 }
 
+/// Event filter of [`Circuit::watch`]. `None` matches everything.
 #[derive(Debug, Clone)]
-struct Signal {
-    from: String,
-    to: String,
-    pulse: Pulse,
+struct Watcher {
+    from: Option<ModuleId>,
+    to: Option<ModuleId>,
+    pulse: Option<Pulse>,
+    hits: Vec<usize>,
 }
 
+impl Watcher {
+    fn observe(&mut self, press: usize, signal: &Signal) {
+        let matches = self.from.is_none_or(|from| from == signal.from)
+            && self.to.is_none_or(|to| to == signal.to)
+            && self.pulse.is_none_or(|pulse| pulse == signal.pulse);
+        if matches && self.hits.last() != Some(&press) {
+            self.hits.push(press);
+        }
+    }
+}
+
+/// The module network together with its state and the number of presses so far.
 #[derive(Debug, Clone)]
-enum Module {
-    Broadcaster(Vec<String>),
-    FlipFlop((String, Vec<String>, Vec<String>, Pulse)),
-    Conjunction((String, Vec<String>, HashMap<String, Pulse>)),
+pub struct Circuit {
+    modules: Vec<Module>,
+    ids: HashMap<String, ModuleId>,
+    button: ModuleId,
+    broadcaster: ModuleId,
+    presses: usize,
+    watchers: Vec<Watcher>,
 }
 
-impl Module {
-    pub fn name(&self) -> &str {
-        match self {
-            Self::Broadcaster(_) => "broadcaster",
-            Self::FlipFlop((name, _, _, _)) => name,
-            Self::Conjunction((name, _, _)) => name,
+impl FromStr for Circuit {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let declarations = lines::<Declaration>(s)?;
+        let mut circuit = Circuit {
+            modules: vec![],
+            ids: HashMap::new(),
+            button: 0,
+            broadcaster: 0,
+            presses: 0,
+            watchers: vec![],
+        };
+        circuit.button = circuit.add("button", Kind::Button);
+        for (line, declaration) in s.lines().zip(&declarations) {
+            if circuit.ids.contains_key(&declaration.name) {
+                return Err(ParseError::new(
+                    offset_in(s, line),
+                    format!("module {} declared twice", declaration.name),
+                ));
+            }
+            circuit.add(&declaration.name, declaration.kind.clone());
+        }
+        circuit.broadcaster = circuit
+            .id("broadcaster")
+            .ok_or_else(|| ParseError::new(0, "missing broadcaster"))?;
+        circuit.connect(circuit.button, circuit.broadcaster);
+        for declaration in &declarations {
+            let from = circuit.ids[&declaration.name];
+            for target in &declaration.targets {
+                let to = match circuit.id(target) {
+                    Some(to) => to,
+                    None => circuit.add(target, Kind::Output),
+                };
+                circuit.connect(from, to);
+            }
         }
+        Ok(circuit)
+    }
+}
+
+impl Circuit {
+    fn add(&mut self, name: &str, kind: Kind) -> ModuleId {
+        let id = self.modules.len();
+        self.ids.insert(name.to_string(), id);
+        self.modules.push(Module {
+            name: name.to_string(),
+            kind,
+            inputs: vec![],
+            targets: vec![],
+        });
+        id
     }
 
-    pub fn target_names(&self) -> Vec<String> {
-        match self {
-            Self::Broadcaster(targets)
-            | Self::FlipFlop((_, _, targets, _))
-            | Self::Conjunction((_, targets, _)) => targets.clone(),
+    fn connect(&mut self, from: ModuleId, to: ModuleId) {
+        self.modules[from].targets.push(to);
+        if self.modules[to].inputs.contains(&from) {
+            return;
+        }
+        self.modules[to].inputs.push(from);
+        if let Kind::Conjunction { memory } = &mut self.modules[to].kind {
+            memory.push(Pulse::Low);
         }
     }
 
-    pub fn register_input(&mut self, input: &Module) {
-        match self {
-            Self::Broadcaster(_) => {}
-            Self::FlipFlop((_, inputs, _, _)) => {
-                inputs.push(input.name().to_string());
+    pub fn id(&self, name: &str) -> Option<ModuleId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: ModuleId) -> &str {
+        &self.modules[id].name
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    pub fn presses(&self) -> usize {
+        self.presses
+    }
+
+    /// Presses the button once and returns every signal in processing order,
+    /// starting with the one from the button to the broadcaster.
+    pub fn press(&mut self) -> Vec<Signal> {
+        self.presses += 1;
+        let mut log = vec![];
+        let mut queue = VecDeque::from([Signal {
+            from: self.button,
+            to: self.broadcaster,
+            pulse: Pulse::Low,
+        }]);
+        while let Some(signal) = queue.pop_front() {
+            for watcher in &mut self.watchers {
+                watcher.observe(self.presses, &signal);
             }
-            Self::Conjunction((_, _, registry)) => {
-                registry.insert(input.name().to_string(), Pulse::Low);
+            let module = &mut self.modules[signal.to];
+            if let Some(pulse) = module.receive(signal.from, signal.pulse) {
+                queue.extend(module.targets.iter().map(|to| Signal {
+                    from: signal.to,
+                    to: *to,
+                    pulse,
+                }));
             }
+            log.push(signal);
         }
+        log
     }
 
-    pub fn handle_signal(&mut self, signal: Signal) -> Vec<Signal> {
-        let own_name = self.name().to_string();
-        match self {
-            Self::Broadcaster(targets) => targets
-                .iter()
-                .map(|t| Signal {
-                    from: own_name.clone(),
-                    to: t.clone(),
-                    pulse: signal.pulse.clone(),
-                })
-                .collect(),
-            Self::FlipFlop((name, _, targets, state)) => {
-                if signal.pulse == Pulse::High {
-                    return vec![];
-                }
-                state.flip();
-                targets
-                    .iter()
-                    .map(|t| Signal {
-                        from: name.clone(),
-                        to: t.clone(),
-                        pulse: state.clone(),
-                    })
-                    .collect()
+    /// Registers a watcher for signals matching all given parts and returns
+    /// its id for [`Circuit::hits`].
+    pub fn watch(
+        &mut self,
+        from: Option<ModuleId>,
+        to: Option<ModuleId>,
+        pulse: Option<Pulse>,
+    ) -> usize {
+        self.watchers.push(Watcher {
+            from,
+            to,
+            pulse,
+            hits: vec![],
+        });
+        self.watchers.len() - 1
+    }
+
+    /// Presses (1-based) during which the watcher saw a matching signal.
+    pub fn hits(&self, watcher: usize) -> &[usize] {
+        &self.watchers[watcher].hits
+    }
+
+    /// States of all modules, usable with [`Circuit::restore`].
+    pub fn snapshot(&self) -> Vec<Kind> {
+        self.modules.iter().map(|m| m.kind.clone()).collect()
+    }
+
+    pub fn restore(&mut self, snapshot: &[Kind]) {
+        for (module, kind) in self.modules.iter_mut().zip(snapshot) {
+            module.kind = kind.clone();
+        }
+    }
+
+    pub fn state_hash(&self) -> u64 {
+        fingerprint(&self.snapshot())
+    }
+
+    /// Presses after which the state of the whole circuit starts repeating.
+    ///
+    /// The state space grows exponentially with the number of flip-flops, so
+    /// this only terminates in reasonable time for small circuits.
+    pub fn find_cycle(&self) -> Cycle {
+        cycle::find_cycle(
+            self.clone(),
+            |circuit| {
+                circuit.press();
+            },
+            Circuit::snapshot,
+        )
+    }
+
+    /// The module network with the same ids as the circuit.
+    pub fn graph(&self) -> Graph<String> {
+        let mut graph = Graph::directed();
+        for module in &self.modules {
+            graph.add_node(module.name.clone());
+        }
+        for (from, module) in self.modules.iter().enumerate() {
+            for to in &module.targets {
+                graph.add_edge_by_id(from, *to, ());
             }
-            Self::Conjunction((name, targets, inputs)) => {
-                inputs.insert(signal.from, signal.pulse);
-                if inputs.values().all(|v| v.clone().into()) {
-                    targets
+        }
+        graph
+    }
+
+    /// Graphviz representation with module kinds as shapes. With `state`,
+    /// flip-flops that are on are filled and inputs a conjunction remembers
+    /// as high are drawn in red.
+    pub fn to_dot(&self, state: bool) -> String {
+        self.graph().to_dot_with(
+            |id, name| {
+                let shape = match self.modules[id].kind {
+                    Kind::Button => "plaintext",
+                    Kind::Broadcaster => "doublecircle",
+                    Kind::FlipFlop { .. } => "box",
+                    Kind::Conjunction { .. } => "diamond",
+                    Kind::Output => "doubleoctagon",
+                };
+                let mut attrs = format!("label={name:?}, shape={shape}");
+                if state && self.modules[id].kind == (Kind::FlipFlop { on: true }) {
+                    attrs.push_str(", style=filled, fillcolor=gold");
+                }
+                attrs
+            },
+            |from, to, _| {
+                let remembers_high = match &self.modules[to].kind {
+                    Kind::Conjunction { memory } => self.modules[to]
+                        .inputs
                         .iter()
-                        .map(|t| Signal {
-                            from: name.clone(),
-                            to: t.clone(),
-                            pulse: Pulse::Low,
-                        })
-                        .collect()
+                        .position(|input| *input == from)
+                        .is_some_and(|input| memory[input] == Pulse::High),
+                    _ => false,
+                };
+                if state && remembers_high {
+                    "color=red, penwidth=2".to_string()
                 } else {
-                    targets
-                        .iter()
-                        .map(|t| Signal {
-                            from: name.clone(),
-                            to: t.clone(),
-                            pulse: Pulse::High,
-                        })
-                        .collect()
+                    String::new()
                 }
-            }
-        }
+            },
+        )
     }
 }
 
-impl FromStr for Module {
-    type Err = ();
+/// A single line like `%a -> b, c` before the names are resolved.
+#[derive(Debug, Clone)]
+struct Declaration {
+    name: String,
+    kind: Kind,
+    targets: Vec<String>,
+}
+
+impl FromStr for Declaration {
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (name, targets) = s.split_once(" -> ").unwrap();
-        let targets = targets.split(", ").map(|s| s.to_string()).collect();
-
-        Ok(match name {
-            "broadcaster" => Self::Broadcaster(targets),
-            name => match name.split_at(1) {
-                ("%", name) => Self::FlipFlop((name.to_owned(), vec![], targets, Pulse::Low)),
-                ("&", name) => Self::Conjunction((name.to_owned(), targets, HashMap::new())),
-                _ => unreachable!("Invalid module"),
-            },
+        let mut cursor = Cursor::new(s);
+        let prefix = ["%", "&"].into_iter().find(|prefix| cursor.eat(prefix));
+        cursor.skip_whitespace();
+        let name_at = cursor.pos();
+        let name = cursor.word()?.to_owned();
+        cursor.literal("->")?;
+        let targets = cursor.sep_by(",", |cursor| cursor.word().map(str::to_string))?;
+        cursor.end()?;
+
+        let kind = match (prefix, name.as_str()) {
+            (None, "broadcaster") => Kind::Broadcaster,
+            (Some("%"), _) => Kind::FlipFlop { on: false },
+            (Some("&"), _) => Kind::Conjunction { memory: vec![] },
+            _ => return Err(ParseError::new(name_at, format!("invalid module {name}"))),
+        };
+        Ok(Declaration {
+            name,
+            kind,
+            targets,
         })
     }
 }
@@ -205,6 +505,16 @@ mod tests {
     use crate::solution::Solution;
     use crate::utils::read_input;
 
+    const COUNTER: &str = "broadcaster -> a\n%a -> inv, con\n&inv -> b\n%b -> con\n&con -> output";
+
+    #[test]
+    fn test_parse_errors() {
+        let input = "broadcaster -> a\n%a -> b\n%a -> b";
+        assert_eq!(input.parse::<Circuit>().unwrap_err().offset, 25);
+        let input = "broadcaster -> a\n  a -> b";
+        assert_eq!(input.parse::<Circuit>().unwrap_err().offset, 19);
+    }
+
     #[test]
     fn test_part1_example() {
         let input = read_input(20, true, 1).unwrap();
@@ -218,12 +528,96 @@ mod tests {
 
     #[test]
     fn test_part2_example() {
+        // The example has no rx module
         let input = read_input(20, true, 2).unwrap();
         assert_eq!(Day.part2(&input), None);
+        assert_eq!(
+            presses_until_low(input.parse().unwrap(), "rx"),
+            Err(CircuitError::Unreachable("rx".to_string()))
+        );
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(20, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        assert_eq!(Day.part2(&input), Some(253302889093151));
+    }
+
+    #[test]
+    fn test_part2_brute_force() {
+        let circuit = "broadcaster -> a\n%a -> b\n%b -> rx".parse().unwrap();
+        assert_eq!(presses_until_low(circuit, "rx"), Ok(4));
+        let circuit = "broadcaster -> a\n%a -> x\n&x -> rx".parse().unwrap();
+        assert_eq!(presses_until_low(circuit, "rx"), Ok(1));
+    }
+
+    #[test]
+    fn test_part2_unexpected_shape() {
+        // rx only ever receives high pulses
+        let circuit = "broadcaster -> a\n&a -> rx".parse().unwrap();
+        assert!(matches!(
+            presses_until_low(circuit, "rx"),
+            Err(CircuitError::UnexpectedShape(_))
+        ));
+    }
+
+    #[test]
+    fn test_press_log_and_watchers() {
+        let mut circuit: Circuit = COUNTER.parse().unwrap();
+        let output = circuit.id("output").unwrap();
+        let watcher = circuit.watch(None, Some(output), Some(Pulse::Low));
+        let log = circuit.press();
+        assert_eq!(log.len(), 8);
+        assert_eq!(circuit.name(log[0].from), "button");
+        assert_eq!(circuit.name(log[1].to), "a");
+        for _ in 0..3 {
+            circuit.press();
+        }
+        assert_eq!(circuit.hits(watcher), &[1, 3]);
+    }
+
+    #[test]
+    fn test_state_and_cycle() {
+        let mut circuit: Circuit = COUNTER.parse().unwrap();
+        let initial = circuit.snapshot();
+        let initial_hash = circuit.state_hash();
+        assert_eq!(
+            circuit.find_cycle(),
+            Cycle {
+                prefix: 0,
+                period: 4
+            }
+        );
+        circuit.press();
+        assert_ne!(circuit.state_hash(), initial_hash);
+        let a = circuit.id("a").unwrap();
+        assert_eq!(circuit.modules()[a].kind, Kind::FlipFlop { on: true });
+        circuit.restore(&initial);
+        assert_eq!(circuit.state_hash(), initial_hash);
+
+        let example: Circuit = read_input(20, true, 1).unwrap().parse().unwrap();
+        assert_eq!(example.find_cycle().period, 1);
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut circuit: Circuit = COUNTER.parse().unwrap();
+        let dot = circuit.to_dot(true);
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("label=\"broadcaster\", shape=doublecircle"));
+        assert!(dot.contains("label=\"con\", shape=diamond"));
+        assert!(!dot.contains("filled"));
+
+        let (a, con) = (circuit.id("a").unwrap(), circuit.id("con").unwrap());
+        circuit.press();
+        let dot = circuit.to_dot(true);
+        assert!(dot.contains(&format!("n{a} [label=\"a\", shape=box, style=filled")));
+        assert!(dot.contains(&format!("n{a} -> n{con} [color=red")));
+        assert!(!circuit.to_dot(false).contains("red"));
+
+        let options = VisualizeOptions {
+            steps: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(Day.visualize(COUNTER, &options), Some(dot));
     }
 }
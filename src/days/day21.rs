@@ -1,6 +1,9 @@
 use std::{fmt::Display, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::math::lcm;
+use crate::utils::parse::{grid, ParseError};
+use crate::utils::poly;
 
 pub struct Day;
 
@@ -18,198 +21,64 @@ impl Solution for Day {
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
-        // let (steps, input) = input.split_once("\n\n").unwrap();
+        // Inputs may override the step count with a leading line of its own
+        let (steps, input) = match input.split_once("\n\n") {
+            Some((steps, map)) if !steps.contains('\n') => (steps.trim().parse().ok()?, map),
+            _ => (PART2_STEPS, input),
+        };
         let map: Map = input.parse().unwrap();
-        let mut total_cells = 0;
-        let map_size = map.tiles.len();
-
-        let wanted_steps = 26501365;
-        // let wanted_steps = 23;
-        // let wanted_steps: usize = steps.parse().unwrap();
-
-        if wanted_steps > map_size / 2 {
-            total_cells += count_1s(&map, wanted_steps);
-            println!("1s: {}", total_cells);
-            total_cells += count_2s(&map, wanted_steps);
-            println!("2s: {}", total_cells);
-            total_cells += count_3s(&map, wanted_steps);
-            println!("3s: {}", total_cells);
-            total_cells += count_4s(&map, wanted_steps);
-            println!("4s: {}", total_cells);
-            total_cells += count_5s(&map, wanted_steps);
-        } else {
-            let res = map.get_distance_even_odd(wanted_steps);
-            total_cells += if wanted_steps % 2 == 0 { res.0 } else { res.1 };
-        }
-        Some(total_cells)
+        map.reachable_infinite(steps).ok()
     }
 }
 
-/*
-    ....1....
-    ...232...
-    ..24542..
-    .2455542.
-    135555531
-    .2455542.
-    ..24542..
-    ...232...
-    ....1....
-*/
+const PART2_STEPS: usize = 26501365;
 
-fn count_1s(map: &Map, wanted_steps: usize) -> usize {
-    println!("1s:");
-    let map_size = map.tiles.len();
-    let steps_tldr = ((wanted_steps - 1) - map_size / 2) % map_size;
-    println!("steps tldr: {}", steps_tldr);
-    let mut clone = map.clone();
-    clone.start = (0, map_size / 2);
-    let s_right = clone.get_distance_even_odd(steps_tldr);
-    clone.start = (map_size / 2, 0);
-    let s_up = clone.get_distance_even_odd(steps_tldr);
-    clone.start = (map_size - 1, map_size / 2);
-    let s_left = clone.get_distance_even_odd(steps_tldr);
-    clone.start = (map_size / 2, map_size - 1);
-    let s_down = clone.get_distance_even_odd(steps_tldr);
-    if steps_tldr % 2 == 0 {
-        s_right.0 + s_up.0 + s_left.0 + s_down.0
-    } else {
-        s_right.1 + s_up.1 + s_left.1 + s_down.1
-    }
-}
+/// Number of step counts, one period apart, that are sampled for extrapolation.
+const SAMPLES: usize = 10;
 
-fn count_2s(map: &Map, wanted_steps: usize) -> usize {
-    println!("2s:");
-    let map_size = map.tiles.len();
-    if wanted_steps > map_size {
-        let steps_in_corner = (wanted_steps - 1 - map_size) % (map_size * 2);
-        let mut clone = map.clone();
-        clone.start = (0, 0);
-        let s_br = clone.get_distance_even_odd(steps_in_corner);
-        clone.start = (map_size - 1, 0);
-        let s_bl = clone.get_distance_even_odd(steps_in_corner);
-        clone.start = (0, map_size - 1);
-        let s_tr = clone.get_distance_even_odd(steps_in_corner);
-        clone.start = (map_size - 1, map_size - 1);
-        let s_tl = clone.get_distance_even_odd(steps_in_corner);
-
-        let s_corners = if steps_in_corner % 2 == 0 {
-            s_br.0 + s_bl.0 + s_tr.0 + s_tl.0
-        } else {
-            s_br.1 + s_bl.1 + s_tr.1 + s_tl.1
-        };
+/// Samples that have to fit a polynomial after the warm-up. Two more than
+/// needed for a quadratic, so that the fit is actually verified.
+const MIN_FIT: usize = 5;
 
-        let corner_count = (wanted_steps - 1) / map_size;
-        println!("2 corner count: {}", corner_count);
-        s_corners * corner_count
-    } else {
-        0
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GardenError {
+    /// The reachable counts did not grow like a polynomial of degree two or
+    /// less within the sampled periods.
+    NotPolynomial,
+    /// The extrapolated count does not fit into a `usize`.
+    Overflow,
 }
 
-fn count_3s(map: &Map, wanted_steps: usize) -> usize {
-    println!("3s:");
-    let map_size = map.tiles.len();
-    let steps_tldr = wanted_steps % map_size;
-    if steps_tldr > map_size / 2 && wanted_steps > map_size {
-        let steps_tldr = steps_tldr + map_size / 2;
-        let mut clone = map.clone();
-        clone.start = (0, map_size / 2);
-        let s_right = clone.get_distance_even_odd(steps_tldr);
-        clone.start = (map_size / 2, 0);
-        let s_up = clone.get_distance_even_odd(steps_tldr);
-        clone.start = (map_size - 1, map_size / 2);
-        let s_left = clone.get_distance_even_odd(steps_tldr);
-        clone.start = (map_size / 2, map_size - 1);
-        let s_down = clone.get_distance_even_odd(steps_tldr);
-
-        if steps_tldr % 2 == 0 {
-            s_right.0 + s_up.0 + s_left.0 + s_down.0
-        } else {
-            s_right.1 + s_up.1 + s_left.1 + s_down.1
+impl Display for GardenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotPolynomial => write!(
+                f,
+                "reachable plots do not grow quadratically within {SAMPLES} sampled periods"
+            ),
+            Self::Overflow => write!(f, "result does not fit into usize"),
         }
-    } else {
-        0
     }
 }
 
-fn count_4s(map: &Map, wanted_steps: usize) -> usize {
-    println!("4s:");
-    let map_size = map.tiles.len();
-    let steps_tldr = (wanted_steps % map_size) - 1;
-
-    if steps_tldr > map_size / 2 {
-        let steps_tldr = steps_tldr + map_size / 2;
-        let mut clone = map.clone();
-        clone.start = (0, 0);
-        let s_br = clone.get_distance_even_odd(steps_tldr);
-        clone.start = ((map_size - 1) / 2, 0);
-        let s_bl = clone.get_distance_even_odd(steps_tldr);
-        clone.start = (map_size - 1, map_size - 1);
-        let s_tl = clone.get_distance_even_odd(steps_tldr);
-        clone.start = (0, map_size - 1);
-        let s_tr = clone.get_distance_even_odd(steps_tldr);
-
-        let edge_count = (wanted_steps - map_size / 2) / map_size - 1;
-        println!("4 edge count: {}", edge_count);
-
-        (if steps_tldr % 2 == 0 {
-            s_br.0 + s_bl.0 + s_tl.0 + s_tr.0
-        } else {
-            s_br.1 + s_bl.1 + s_tl.1 + s_tr.1
-        }) * edge_count
-    } else {
-        0
-    }
-}
-
-fn count_5s(map: &Map, wanted_steps: usize) -> usize {
-    println!("5s:");
-    let map_size = map.tiles.len();
-    let square_distance = wanted_steps / map_size;
-    let number_of_full_even = number_of_squares(square_distance - (square_distance + 1) % 2);
-    let number_of_full_odd = number_of_squares(square_distance - square_distance % 2);
-    println!("number of full even: {}", number_of_full_even);
-    println!("number of full odd: {}", number_of_full_odd);
-    let (full_map_even, full_map_odd) = map.get_distance_even_odd(map_size);
-
-    if wanted_steps % 2 == 0 {
-        full_map_even * number_of_full_even + full_map_odd * number_of_full_odd
-    } else {
-        full_map_even * number_of_full_odd + full_map_odd * number_of_full_even
-    }
-}
-
-fn number_of_squares(square_distance: usize) -> usize {
-    if square_distance >= 2 {
-        (square_distance - 1) * 4 + number_of_squares(square_distance - 2)
-    } else {
-        square_distance
-    }
-}
-
-fn print_distances(distance_map: &[Vec<Option<usize>>], map: &Map) {
-    for (y, row) in distance_map.iter().enumerate() {
-        for (x, distance) in row.iter().enumerate() {
-            print!(
-                "{} ",
-                match distance {
-                    Some(d) => format!("{:02}", d),
-                    None => match map.get((x, y)) {
-                        Some(Tile::Plot) => "..".to_string(),
-                        Some(Tile::Rock) => "##".to_string(),
-                        Some(Tile::Start) => "SS".to_string(),
-                        None => "??".to_string(),
-                    },
-                }
-            );
-        }
-        println!();
-    }
+impl std::error::Error for GardenError {}
+
+/// Plots reachable in exactly `steps` steps, given the number of plots at
+/// every exact distance. A plot at distance `d` can be reached in `steps`
+/// steps if `d <= steps` and both have the same parity.
+fn reachable_from_layers(layers: &[usize], steps: usize) -> usize {
+    layers
+        .iter()
+        .take(steps + 1)
+        .enumerate()
+        .filter(|(distance, _)| distance % 2 == steps % 2)
+        .map(|(_, count)| count)
+        .sum()
 }
 
+/// The garden with the starting position, repeated infinitely in part 2.
 #[derive(Debug, Clone)]
-struct Map {
+pub struct Map {
     tiles: Vec<Vec<Tile>>,
     start: (usize, usize),
 }
@@ -278,34 +147,91 @@ impl Map {
         distance_map
     }
 
-    pub fn get_distance_even_odd(&self, max_distance: usize) -> (usize, usize) {
-        let distance_map = self.calc_distance_map(max_distance);
-        println!("distance map:");
-        print_distances(&distance_map, self);
-        let res = distance_map
-            .iter()
-            .flatten()
-            .flatten()
-            .fold((0, 0), |acc, item| {
-                if item % 2 == 0 {
-                    (acc.0 + 1, acc.1)
-                } else {
-                    (acc.0, acc.1 + 1)
+    /// Number of plots at every exact distance from the start on the
+    /// infinitely tiled map, for distances up to `max_steps`.
+    fn layer_counts(&self, max_steps: usize) -> Vec<usize> {
+        let (width, height) = (self.tiles[0].len() as i64, self.tiles.len() as i64);
+        let (start_x, start_y) = (self.start.0 as i64, self.start.1 as i64);
+        // Coordinates are relative to the start and never leave this square
+        let size = 2 * max_steps + 1;
+        let index = |(x, y): (i64, i64)| {
+            (y + max_steps as i64) as usize * size + (x + max_steps as i64) as usize
+        };
+        let walkable = |(x, y): (i64, i64)| {
+            self.tiles[(start_y + y).rem_euclid(height) as usize]
+                [(start_x + x).rem_euclid(width) as usize]
+                .is_walkable()
+        };
+
+        let mut visited = vec![false; size * size];
+        visited[index((0, 0))] = true;
+        let mut frontier = vec![(0, 0)];
+        let mut layers = vec![];
+        while !frontier.is_empty() {
+            layers.push(frontier.len());
+            if layers.len() > max_steps {
+                break;
+            }
+            let mut next = vec![];
+            for (x, y) in frontier {
+                for neighbor in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                    if walkable(neighbor) && !visited[index(neighbor)] {
+                        visited[index(neighbor)] = true;
+                        next.push(neighbor);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        layers.resize(max_steps + 1, 0);
+        layers
+    }
+
+    /// Plots reachable in exactly `steps` steps on the infinitely tiled map,
+    /// by plain BFS. Reference for [`Map::reachable_infinite`].
+    pub fn reachable_brute_force(&self, steps: usize) -> usize {
+        reachable_from_layers(&self.layer_counts(steps), steps)
+    }
+
+    /// Plots reachable in exactly `steps` steps on the infinitely tiled map.
+    ///
+    /// Once the BFS front has left the first few tiles, the reachable count
+    /// at `steps % period + k * period` is a quadratic polynomial in `k`.
+    /// This samples several periods, checks that the samples after some
+    /// warm-up really fit a polynomial of degree two or less and
+    /// extrapolates. If that fails with the tile size as period, double the
+    /// period is tried, which covers tiles with alternating parity.
+    pub fn reachable_infinite(&self, steps: usize) -> Result<usize, GardenError> {
+        let tile = lcm(self.tiles.len(), self.tiles[0].len());
+        for period in [tile, 2 * tile] {
+            let remainder = steps % period;
+            let max_sampled = remainder + (SAMPLES - 1) * period;
+            if steps <= max_sampled {
+                return Ok(self.reachable_brute_force(steps));
+            }
+            let layers = self.layer_counts(max_sampled);
+            let samples = (0..SAMPLES)
+                .map(|k| reachable_from_layers(&layers, remainder + k * period) as i64)
+                .collect::<Vec<_>>();
+            for warm_up in 0..=SAMPLES - MIN_FIT {
+                let fitted = &samples[warm_up..];
+                if !matches!(poly::degree(fitted), Ok(Some(degree)) if degree <= 2) {
+                    continue;
                 }
-            });
-        println!("even: {}, odd: {}", res.0, res.1);
-        res
+                let index = ((steps - remainder) / period - warm_up) as i64;
+                let value = poly::extrapolate(fitted, index).map_err(|_| GardenError::Overflow)?;
+                return usize::try_from(value).map_err(|_| GardenError::Overflow);
+            }
+        }
+        Err(GardenError::NotPolynomial)
     }
 }
 
 impl FromStr for Map {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let tiles: Vec<Vec<Tile>> = s
-            .lines()
-            .map(|line| line.chars().map(Tile::from).collect())
-            .collect();
+        let tiles: Vec<Vec<Tile>> = grid(s)?;
         let start = tiles
             .iter()
             .enumerate()
@@ -318,7 +244,7 @@ impl FromStr for Map {
                     }
                 })
             })
-            .unwrap();
+            .ok_or_else(|| ParseError::new(0, "map has no start tile"))?;
         Ok(Map { tiles, start })
     }
 }
@@ -385,15 +311,48 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn test_part2_example() {
         let input = read_input(21, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), Some(609453319569496));
+        assert_eq!(Day.part2(&input), Some(576));
     }
     #[test]
-    #[ignore]
     fn test_part2_challenge() {
         let input = read_input(21, false, 2).unwrap();
         assert_eq!(Day.part2(&input), Some(619407349431167));
     }
+
+    #[test]
+    fn test_infinite_example_steps() {
+        let map: Map = read_input(21, true, 1).unwrap().parse().unwrap();
+        for (steps, expected) in [
+            (6, 16),
+            (10, 50),
+            (50, 1594),
+            (100, 6536),
+            (500, 167004),
+            (1000, 668697),
+            (5000, 16733044),
+        ] {
+            assert_eq!(map.reachable_infinite(steps), Ok(expected));
+        }
+    }
+
+    #[test]
+    fn test_brute_force_reference() {
+        let map: Map = read_input(21, true, 1).unwrap().parse().unwrap();
+        for steps in [0, 1, 6, 64, 150, 333] {
+            assert_eq!(
+                map.reachable_infinite(steps),
+                Ok(map.reachable_brute_force(steps))
+            );
+        }
+        // Not square and the start is off-centre
+        let map: Map = "..#....\n.S..#..\n...#...\n#......".parse().unwrap();
+        for steps in [3, 40, 117, 301] {
+            assert_eq!(
+                map.reachable_infinite(steps),
+                Ok(map.reachable_brute_force(steps))
+            );
+        }
+    }
 }
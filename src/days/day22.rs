@@ -1,173 +1,229 @@
-use std::{
-    collections::{HashMap, HashSet},
-    str::FromStr,
-};
+use std::str::FromStr;
 
 use crate::solution::Solution;
+use crate::utils::graph::Graph;
+use crate::utils::parse::{array, lines, Cursor, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let mut bricks = input
-            .lines()
-            .map(|l| l.parse().unwrap())
-            .enumerate()
-            .map(|(i, mut b): (usize, Brick)| {
-                b.id = Some(i);
-                b
-            })
-            .collect::<Vec<Brick>>();
-        bricks.sort_by_key(|b| b.min_z());
-        let mut supported_blocks = HashMap::new();
-        let mut supporting_blocks: HashMap<Id, HashSet<Id>> = HashMap::new();
-        for brick in bricks.iter_mut() {
-            brick.ground(&mut supported_blocks);
-            supporting_blocks.insert(brick.id.unwrap(), brick.supported_by.clone());
-        }
-        // println!("{:?}", supporting_blocks);
-        for (id, supporting) in supporting_blocks.iter() {
-            for supporting in supporting.iter() {
-                bricks[*supporting].supporting.insert(*id);
-            }
-        }
-        // for brick in bricks.iter() {
-        //     println!("{:?} : {:?}", brick, brick.could_be_desintegrated(&bricks));
-        // }
-        Some(
-            bricks
-                .iter()
-                .filter(|b| b.could_be_desintegrated(&bricks))
-                .count(),
-        )
+        let stack: Stack = input.parse().unwrap();
+        Some(stack.ids().filter(|id| stack.is_safe(*id)).count())
     }
 
-    fn part2(&self, _input: &str) -> Option<usize> {
-        None
+    fn part2(&self, input: &str) -> Option<usize> {
+        let stack: Stack = input.parse().unwrap();
+        Some(stack.fall_counts().iter().sum())
     }
 }
 
+/// Number of other bricks that would fall if the brick from the given input
+/// line was disintegrated.
+pub fn fall_counts(input: &str) -> Result<Vec<usize>, ParseError> {
+    Ok(input.parse::<Stack>()?.fall_counts())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Position {
-    x: usize,
-    y: usize,
-    z: usize,
+pub struct Position {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
 }
 
 impl FromStr for Position {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts = s.split(',').collect::<Vec<_>>();
-        Ok(Position {
-            x: parts[0].parse().unwrap(),
-            y: parts[1].parse().unwrap(),
-            z: parts[2].parse().unwrap(),
-        })
+        let [x, y, z] = array(s, ",")?;
+        Ok(Position { x, y, z })
     }
 }
 
-type Id = usize;
+/// Index of a brick, which is its line in the input.
+pub type Id = usize;
 
-#[derive(Debug)]
-struct Brick {
-    start: Position,
-    end: Position,
-    id: Option<Id>,
-    supported_by: HashSet<Id>,
-    supporting: HashSet<Id>,
+/// A straight line of cubes. `start` is the lower corner in every axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Brick {
+    pub start: Position,
+    pub end: Position,
 }
 
 impl Brick {
-    pub fn min_x(&self) -> usize {
-        self.start.x.min(self.end.x)
+    /// Every `(x, y)` column the brick occupies.
+    fn footprint(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (start, end) = (self.start, self.end);
+        (start.x..=end.x).flat_map(move |x| (start.y..=end.y).map(move |y| (x, y)))
     }
 
-    pub fn max_x(&self) -> usize {
-        self.start.x.max(self.end.x)
+    fn height(&self) -> usize {
+        self.end.z - self.start.z + 1
     }
+}
 
-    pub fn min_y(&self) -> usize {
-        self.start.y.min(self.end.y)
-    }
+impl FromStr for Brick {
+    type Err = ParseError;
 
-    pub fn max_y(&self) -> usize {
-        self.start.y.max(self.end.y)
-    }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cursor = Cursor::new(s);
+        let a: Position = cursor.token(|c| c == '~')?;
+        cursor.literal("~")?;
+        cursor.skip_whitespace();
+        let b_at = cursor.pos();
+        let b: Position = cursor.token(|_| false)?;
 
-    pub fn min_z(&self) -> usize {
-        self.start.z
+        let differing = [a.x != b.x, a.y != b.y, a.z != b.z]
+            .iter()
+            .filter(|d| **d)
+            .count();
+        if differing > 1 {
+            return Err(ParseError::new(b_at, "brick is not axis-aligned"));
+        }
+        if a.z == 0 || b.z == 0 {
+            let at = if a.z == 0 { 0 } else { b_at };
+            return Err(ParseError::new(
+                at,
+                "brick is not above the ground at z = 0",
+            ));
+        }
+        Ok(Brick {
+            start: Position {
+                x: a.x.min(b.x),
+                y: a.y.min(b.y),
+                z: a.z.min(b.z),
+            },
+            end: Position {
+                x: a.x.max(b.x),
+                y: a.y.max(b.y),
+                z: a.z.max(b.z),
+            },
+        })
     }
+}
+
+/// All bricks after they settled, indexed by their id.
+#[derive(Debug, Clone)]
+pub struct Stack {
+    bricks: Vec<Brick>,
+    supports: Vec<Vec<Id>>,
+    supported_by: Vec<Vec<Id>>,
+}
+
+impl FromStr for Stack {
+    type Err = ParseError;
 
-    pub fn max_z(&self) -> usize {
-        self.end.z
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Stack::settle(lines(s)?))
     }
+}
 
-    pub fn ground(&mut self, supported_blocks: &mut HashMap<(usize, usize), (usize, Option<Id>)>) {
-        let mut max_supported_level = 0;
-        let mut supported_by = HashSet::new();
+impl Stack {
+    /// Lets the bricks fall, lowest first, keeping a height map of the
+    /// highest brick in every column.
+    pub fn settle(mut bricks: Vec<Brick>) -> Self {
+        let width = bricks.iter().map(|b| b.end.x + 1).max().unwrap_or(0);
+        let depth = bricks.iter().map(|b| b.end.y + 1).max().unwrap_or(0);
+        let mut height_map: Vec<Vec<(usize, Option<Id>)>> = vec![vec![(0, None); depth]; width];
+        let mut supports = vec![vec![]; bricks.len()];
+        let mut supported_by = vec![vec![]; bricks.len()];
 
-        for x in self.min_x()..=self.max_x() {
-            for y in self.min_y()..=self.max_y() {
-                let (level, id) = **supported_blocks
-                    .get(&(x, y))
-                    .get_or_insert(&(0, None));
-                if level > max_supported_level {
-                    max_supported_level = level;
-                    supported_by = HashSet::new();
-                }
-                if level == max_supported_level {
-                    if let Some(id) = id {
-                        supported_by.insert(id);
+        let mut order = (0..bricks.len()).collect::<Vec<Id>>();
+        order.sort_by_key(|id| bricks[*id].start.z);
+        for id in order {
+            let brick = &mut bricks[id];
+            let level = brick
+                .footprint()
+                .map(|(x, y)| height_map[x][y].0)
+                .max()
+                .unwrap_or(0);
+            for (x, y) in brick.footprint() {
+                if let (height, Some(below)) = height_map[x][y] {
+                    if height == level && !supported_by[id].contains(&below) {
+                        supported_by[id].push(below);
+                        supports[below].push(id);
                     }
                 }
             }
+            let height = brick.height();
+            brick.start.z = level + 1;
+            brick.end.z = level + height;
+            for (x, y) in brick.footprint() {
+                height_map[x][y] = (brick.end.z, Some(id));
+            }
         }
 
-        let move_down = self.min_z() - 1 - max_supported_level;
-        self.supported_by = supported_by;
-        self.start.z -= move_down;
-        self.end.z -= move_down;
-        for x in self.min_x()..=self.max_x() {
-            for y in self.min_y()..=self.max_y() {
-                supported_blocks.insert((x, y), (self.max_z(), self.id));
-            }
+        Stack {
+            bricks,
+            supports,
+            supported_by,
         }
     }
 
-    pub fn could_be_desintegrated(&self, bricks: &[Brick]) -> bool {
-        self.supporting
+    pub fn len(&self) -> usize {
+        self.bricks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bricks.is_empty()
+    }
+
+    pub fn ids(&self) -> std::ops::Range<Id> {
+        0..self.bricks.len()
+    }
+
+    /// Settled position of a brick.
+    pub fn brick(&self, id: Id) -> &Brick {
+        &self.bricks[id]
+    }
+
+    /// Bricks directly resting on `id`.
+    pub fn supports(&self, id: Id) -> &[Id] {
+        &self.supports[id]
+    }
+
+    /// Bricks `id` directly rests on. Empty for bricks on the ground.
+    pub fn supported_by(&self, id: Id) -> &[Id] {
+        &self.supported_by[id]
+    }
+
+    /// Whether `id` can be disintegrated without any other brick falling.
+    pub fn is_safe(&self, id: Id) -> bool {
+        self.supports[id]
             .iter()
-            .all(|id| bricks[*id].supported_by.len() != 1)
+            .all(|above| self.supported_by[*above].len() > 1)
     }
-}
 
-impl FromStr for Brick {
-    type Err = ();
+    /// Number of other bricks that would fall for every brick.
+    ///
+    /// A brick falls when `x` is removed exactly if every path from the ground
+    /// passes through `x`, so the bricks falling for `x` are the ones it
+    /// dominates in the support graph.
+    pub fn fall_counts(&self) -> Vec<usize> {
+        let graph = self.support_graph();
+        let sizes = graph.dominators(0).subtree_sizes();
+        self.ids()
+            .map(|id| sizes[graph.id(&Some(id)).unwrap()] - 1)
+            .collect()
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (start, end) = s.split_once('~').unwrap();
-        let start_pos: Position = start.parse().unwrap();
-        let end_pos: Position = end.parse().unwrap();
-
-        // Make sure that end is not lower than start
-        if start_pos.z <= end_pos.z {
-            Ok(Brick {
-                start: start_pos,
-                end: end_pos,
-                id: None,
-                supported_by: HashSet::new(),
-                supporting: HashSet::new(),
-            })
-        } else {
-            Ok(Brick {
-                start: end_pos,
-                end: start_pos,
-                id: None,
-                supported_by: HashSet::new(),
-                supporting: HashSet::new(),
-            })
+    /// Edges from every brick to the bricks resting on it, with the ground as
+    /// node 0 below all bricks without support.
+    fn support_graph(&self) -> Graph<Option<Id>> {
+        let mut graph = Graph::directed();
+        let ground = graph.add_node(None);
+        for id in self.ids() {
+            let node = graph.add_node(Some(id));
+            if self.supported_by[id].is_empty() {
+                graph.add_edge_by_id(ground, node, ());
+            }
         }
+        for id in self.ids() {
+            for above in &self.supports[id] {
+                graph.add_edge(Some(id), Some(*above), ());
+            }
+        }
+        graph
     }
 }
 
@@ -191,11 +247,64 @@ mod tests {
     #[test]
     fn test_part2_example() {
         let input = read_input(22, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        assert_eq!(Day.part2(&input), Some(7));
+        assert_eq!(fall_counts(&input), Ok(vec![6, 0, 0, 0, 0, 1, 0]));
     }
     #[test]
     fn test_part2_challenge() {
         let input = read_input(22, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        assert_eq!(Day.part2(&input), Some(63491));
+    }
+
+    #[test]
+    fn test_queries() {
+        let stack: Stack = read_input(22, true, 1).unwrap().parse().unwrap();
+        assert_eq!(stack.supports(0), &[1, 2]);
+        assert_eq!(stack.supported_by(3), &[1, 2]);
+        assert!(stack.supported_by(0).is_empty());
+        assert!(!stack.is_safe(0));
+        assert!(stack.is_safe(1));
+        assert_eq!(stack.brick(6).start.z, 5);
+        assert_eq!(stack.brick(6).end.z, 6);
+    }
+
+    #[test]
+    fn test_shuffled_input() {
+        let input = read_input(22, true, 1).unwrap();
+        let lines = input.lines().collect::<Vec<_>>();
+        let original: Stack = input.parse().unwrap();
+        let counts = original.fall_counts();
+        // line i of the shuffled input is line order[i] of the original
+        for step in [1, 3, 5, 6] {
+            let order = (0..lines.len())
+                .map(|i| (i * step + 2) % lines.len())
+                .collect::<Vec<_>>();
+            let shuffled = order.iter().map(|i| lines[*i]).collect::<Vec<_>>();
+            let stack: Stack = shuffled.join("\n").parse().unwrap();
+            let shuffled_counts = stack.fall_counts();
+            for (id, original_id) in order.iter().enumerate() {
+                assert_eq!(stack.brick(id), original.brick(*original_id));
+                assert_eq!(stack.is_safe(id), original.is_safe(*original_id));
+                assert_eq!(shuffled_counts[id], counts[*original_id]);
+            }
+        }
+
+        let input = read_input(22, false, 1).unwrap();
+        let reversed = input.lines().rev().collect::<Vec<_>>().join("\n");
+        assert_eq!(Day.part1(&reversed), Some(401));
+        assert_eq!(Day.part2(&reversed), Some(63491));
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!("1,0,1~1,2,2".parse::<Brick>().unwrap_err().offset, 6);
+        assert_eq!("1,0,0~1,2,0".parse::<Brick>().unwrap_err().offset, 0);
+        assert_eq!("1,0,1~1,0,0".parse::<Brick>().unwrap_err().offset, 6);
+        let brick: Brick = "1,1,9~1,1,8".parse().unwrap();
+        assert_eq!(brick.start.z, 8);
+
+        let input = "1,0,1~1,2,1\n0,0,2~2,2,2";
+        let error = input.parse::<Stack>().unwrap_err();
+        assert_eq!(error.line_col(input).0, 2);
     }
 }
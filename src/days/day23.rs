@@ -4,199 +4,248 @@ use std::{
     str::FromStr,
 };
 
-use crate::solution::Solution;
+use rayon::prelude::*;
+
+use crate::solution::{Solution, VisualizeOptions};
+use crate::utils::graph::{Graph, NodeId};
+use crate::utils::parse::{grid, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
         let map = Map::from_str(input).unwrap();
-        let from = Position { x: 1, y: 0 };
-        let to = Position {
-            x: map.tiles[0].len() - 2,
-            y: map.tiles.len() - 1,
-        };
-        let longest_path = map
-            .find_longest_path(from, to, Path::possible_directions_block_slopes)
-            .unwrap();
-        Some(longest_path.len() - 1)
+        let (from, to) = map.endpoints()?;
+        map.junctions(from, to, true).longest_path(true)
     }
 
     fn part2(&self, input: &str) -> Option<usize> {
         let map = Map::from_str(input).unwrap();
-        let from = Position { x: 1, y: 0 };
-        let to = Position {
-            x: map.tiles[0].len() - 2,
-            y: map.tiles.len() - 1,
-        };
-        let longest_path = map
-            .find_longest_path(from, to, Path::possible_directions)
-            .unwrap();
-        println!("{}", longest_path);
-        Some(longest_path.len() - 1)
+        let (from, to) = map.endpoints()?;
+        map.junctions(from, to, false).longest_path(true)
+    }
+
+    /// Overlays the longest hike on the map, following the slopes unless
+    /// part 2 is requested.
+    fn visualize(&self, input: &str, options: &VisualizeOptions) -> Option<String> {
+        let map = Map::from_str(input).ok()?;
+        let (from, to) = map.endpoints()?;
+        let route = map
+            .junctions(from, to, !options.part2)
+            .longest_route(true)?;
+        Some(format!(
+            "Longest hike: {} steps\n{}",
+            route.len() - 1,
+            map.render(&route, !options.plain)
+        ))
     }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-struct Position {
-    x: usize,
-    y: usize,
+pub struct Position {
+    pub x: usize,
+    pub y: usize,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-struct Path {
-    visited: HashSet<Position>,
-    path: Vec<Position>,
+/// Number of junctions a search state has to pass before the remaining
+/// branches are handed to rayon.
+const PARALLEL_DEPTH: usize = 8;
+
+/// The maze reduced to its junctions, the start and the goal. Every edge
+/// holds the tiles of its corridor, ending with the junction it leads to.
+#[derive(Debug, Clone)]
+pub struct Junctions {
+    graph: Graph<Position, Vec<Position>>,
+    start: NodeId,
+    goal: NodeId,
 }
 
-impl Path {
-    pub fn new(start: Position) -> Self {
-        let mut visited = HashSet::new();
-        visited.insert(start);
-        Path {
-            visited,
-            path: vec![start],
-        }
+/// Partial route through the junction graph: current node, visited set and
+/// length so far.
+type State = (NodeId, u128, usize);
+
+impl Junctions {
+    /// The compressed maze, keyed by junction position.
+    pub fn graph(&self) -> &Graph<Position, Vec<Position>> {
+        &self.graph
+    }
+
+    pub fn start(&self) -> NodeId {
+        self.start
     }
 
-    pub fn len(&self) -> usize {
-        self.path.len()
+    pub fn goal(&self) -> NodeId {
+        self.goal
     }
 
-    pub fn current_position(&self) -> Position {
-        *self.path.last().unwrap()
+    /// Length of the longest route from start to goal that visits no tile
+    /// twice, or `None` if the goal cannot be reached.
+    ///
+    /// Visited junctions are a bitmask, so this gives up with `None` for more
+    /// than 128 of them.
+    /// With `parallel`, the search tree is expanded a few levels and the
+    /// subtrees are searched with rayon.
+    pub fn longest_path(&self, parallel: bool) -> Option<usize> {
+        let (search, bonus) = self.search()?;
+        search
+            .longest_from((self.start, 1 << self.start, 0), parallel)
+            .map(|length| length + bonus)
     }
 
-    pub fn is_visited(&self, position: Position) -> bool {
-        self.visited.contains(&position)
+    /// Tiles of a longest route, from start to goal.
+    pub fn longest_route(&self, parallel: bool) -> Option<Vec<Position>> {
+        let (search, _) = self.search()?;
+        let start = (self.start, 1 << self.start, 0);
+        let length = search.longest_from(start, parallel)?;
+        let mut route = vec![*self.graph.key(self.start)];
+        let mut node = self.start;
+        for index in search.route(start, length)? {
+            let (next, corridor) = self.graph.neighbors(node).nth(index)?;
+            route.extend(corridor);
+            node = next;
+        }
+        if node != self.goal {
+            let (_, corridor) = self
+                .graph
+                .neighbors(node)
+                .find(|(next, _)| *next == self.goal)?;
+            route.extend(corridor);
+        }
+        Some(route)
     }
 
-    fn is_slope_blocked(&self, direction: Direction, map: &Map) -> bool {
-        let next_position = self.pos_in_direction(direction).unwrap();
-        let current_tile = map.get(next_position.x, next_position.y).unwrap();
-        if let Tile::Slope(dir) = current_tile {
-            dir != direction
+    /// Search over corridor lengths, and the length that is added once the
+    /// search reaches its target. `None` if the junctions do not fit into the
+    /// visited bitmask.
+    fn search(&self) -> Option<(Search, usize)> {
+        if self.graph.len() > u128::BITS as usize {
+            return None;
+        }
+        let adjacency = self
+            .graph
+            .node_ids()
+            .map(|id| {
+                self.graph
+                    .neighbors(id)
+                    .map(|(next, corridor)| (next, corridor.len()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // Once the only junction leading to the goal is reached, every other
+        // way would cut off the goal, so go there directly.
+        let (target, bonus) = match self.graph.predecessors(self.goal).collect::<Vec<_>>()[..] {
+            [(last, corridor)] if last != self.start => (last, corridor.len()),
+            _ => (self.goal, 0),
+        };
+        Some((Search { adjacency, target }, bonus))
+    }
+}
+
+struct Search {
+    adjacency: Vec<Vec<(NodeId, usize)>>,
+    target: NodeId,
+}
+
+impl Search {
+    fn longest_from(&self, start: State, parallel: bool) -> Option<usize> {
+        if parallel {
+            let mut states = vec![];
+            let mut best = None;
+            self.expand(start, PARALLEL_DEPTH, &mut states, &mut best);
+            states
+                .into_par_iter()
+                .map(|state| self.longest(state))
+                .max()
+                .flatten()
+                .max(best)
         } else {
-            false
+            self.longest(start)
         }
     }
 
-    fn pos_in_direction(&self, direction: Direction) -> Option<Position> {
-        let current_position = self.current_position();
-        match direction {
-            Direction::Up => {
-                if current_position.y > 0 {
-                    Some(Position {
-                        x: current_position.x,
-                        y: current_position.y - 1,
-                    })
-                } else {
-                    None
-                }
-            }
-            Direction::Down => Some(Position {
-                x: current_position.x,
-                y: current_position.y + 1,
-            }),
-            Direction::Left => {
-                if current_position.x > 0 {
-                    Some(Position {
-                        x: current_position.x - 1,
-                        y: current_position.y,
-                    })
-                } else {
-                    None
-                }
-            }
-            Direction::Right => Some(Position {
-                x: current_position.x + 1,
-                y: current_position.y,
-            }),
+    /// Longest route from the given state to the target.
+    fn longest(&self, (node, visited, length): State) -> Option<usize> {
+        if node == self.target {
+            return Some(length);
         }
+        self.adjacency[node]
+            .iter()
+            .filter(|(next, _)| visited & (1 << next) == 0)
+            .filter_map(|(next, weight)| {
+                self.longest((*next, visited | (1 << next), length + weight))
+            })
+            .max()
     }
 
-    pub fn possible_directions_block_slopes(&self, map: &Map) -> Vec<Direction> {
-        let current_position = self.current_position();
-        let current_tile = map.get(current_position.x, current_position.y).unwrap();
-        if let Tile::Slope(direction) = current_tile {
-            if self.is_visited(Position {
-                x: current_position.x + 1,
-                y: current_position.y,
-            }) {
-                return vec![];
-            } else {
-                return vec![direction];
-            }
+    /// Indices into the adjacency lists along some route from `state` to the
+    /// target that has exactly the given length.
+    fn route(&self, (node, visited, length): State, wanted: usize) -> Option<Vec<usize>> {
+        if node == self.target {
+            return (length == wanted).then(Vec::new);
         }
-        let possible = self.possible_directions(map);
-        possible
+        self.adjacency[node]
             .iter()
-            .filter(|d| !self.is_slope_blocked(**d, map))
-            .copied()
-            .collect()
-    }
-    pub fn possible_directions(&self, map: &Map) -> Vec<Direction> {
-        [
-            Direction::Up,
-            Direction::Down,
-            Direction::Left,
-            Direction::Right,
-        ]
-        .iter()
-        .filter(|d| {
-            let next_position = self.pos_in_direction(**d);
-            next_position.is_some()
-                && !self.is_visited(next_position.unwrap())
-                && !matches!(
-                    map.get(next_position.unwrap().x, next_position.unwrap().y),
-                    Some(Tile::Forest)
-                )
-        })
-        .copied()
-        .collect::<Vec<Direction>>()
-    }
-
-    pub fn move_to(&mut self, direction: Direction) {
-        let current_position = self.current_position();
-        let new_position = match direction {
-            Direction::Up => Position {
-                x: current_position.x,
-                y: current_position.y - 1,
-            },
-            Direction::Down => Position {
-                x: current_position.x,
-                y: current_position.y + 1,
-            },
-            Direction::Left => Position {
-                x: current_position.x - 1,
-                y: current_position.y,
-            },
-            Direction::Right => Position {
-                x: current_position.x + 1,
-                y: current_position.y,
-            },
-        };
-        self.visited.insert(new_position);
-        self.path.push(new_position);
+            .enumerate()
+            .filter(|(_, (next, weight))| visited & (1 << next) == 0 && length + weight <= wanted)
+            .find_map(|(index, (next, weight))| {
+                let mut route =
+                    self.route((*next, visited | (1 << next), length + weight), wanted)?;
+                route.insert(0, index);
+                Some(route)
+            })
     }
-}
 
-impl Display for Path {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for line in 0..self.path.iter().map(|p| p.y).max().unwrap() + 1 {
-            for column in 0..self.path.iter().map(|p| p.x).max().unwrap() + 1 {
-                let position = Position { x: column, y: line };
-                if self.path.contains(&position) {
-                    write!(f, "X")?;
-                } else if self.visited.contains(&position) {
-                    write!(f, "O")?;
-                } else {
-                    write!(f, ".")?;
+    /// Collects the states `depth` junctions away from `state`, recording
+    /// routes that hit the target earlier in `best`.
+    fn expand(
+        &self,
+        state: State,
+        depth: usize,
+        states: &mut Vec<State>,
+        best: &mut Option<usize>,
+    ) {
+        let (node, visited, length) = state;
+        if node == self.target {
+            *best = (*best).max(Some(length));
+        } else if depth == 0 {
+            states.push(state);
+        } else {
+            for (next, weight) in &self.adjacency[node] {
+                if visited & (1 << next) == 0 {
+                    self.expand(
+                        (*next, visited | (1 << next), length + weight),
+                        depth - 1,
+                        states,
+                        best,
+                    );
                 }
             }
-            writeln!(f)?;
         }
-        Ok(())
+    }
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    fn step(self, position: Position) -> Option<Position> {
+        let Position { x, y } = position;
+        match self {
+            Direction::Up => Some(Position {
+                x,
+                y: y.checked_sub(1)?,
+            }),
+            Direction::Down => Some(Position { x, y: y + 1 }),
+            Direction::Left => Some(Position {
+                x: x.checked_sub(1)?,
+                y,
+            }),
+            Direction::Right => Some(Position { x: x + 1, y }),
+        }
     }
 }
 
@@ -246,8 +295,9 @@ impl From<char> for Tile {
     }
 }
 
+/// The hiking trail map.
 #[derive(Debug, Clone)]
-struct Map {
+pub struct Map {
     tiles: Vec<Vec<Tile>>,
 }
 
@@ -256,53 +306,131 @@ impl Map {
         self.tiles.get(y).and_then(|line| line.get(x)).copied()
     }
 
-    pub fn find_longest_path(
+    /// Start and goal from the only two gaps in the surrounding forest. The
+    /// gap that comes first in reading order is the start.
+    pub fn endpoints(&self) -> Option<(Position, Position)> {
+        let (width, height) = (self.tiles.first()?.len(), self.tiles.len());
+        let gaps = self
+            .tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.iter()
+                    .enumerate()
+                    .filter(move |(x, tile)| {
+                        **tile != Tile::Forest
+                            && (y == 0 || y == height - 1 || *x == 0 || *x == width - 1)
+                    })
+                    .map(move |(x, _)| Position { x, y })
+            })
+            .collect::<Vec<_>>();
+        match gaps[..] {
+            [from, to] => Some((from, to)),
+            _ => None,
+        }
+    }
+
+    /// Directions that can be taken from `position`. With `slopes`, a slope
+    /// can only be entered and left in its own direction.
+    fn moves(
         &self,
-        from: Position,
-        to: Position,
-        direction_finder: fn(&Path, &Map) -> Vec<Direction>,
-    ) -> Option<Box<Path>> {
-        let mut queue = Vec::new();
-        queue.push(Path::new(from));
-        let mut longest: Option<Box<Path>> = None;
-        while let Some(path) = queue.pop() {
-            let current_position = path.current_position();
-            if current_position == to {
-                if let Some(longest) = longest.as_mut() {
-                    if path.len() > longest.len() {
-                        *longest = Box::new(path.clone());
-                        print!("{esc}c", esc = 27 as char);
-                        println!(
-                            "Found path of length {:?}",
-                            longest.len()
-                        );
-                        println!("{longest}");
-                    }
-                } else {
-                    longest = Some(Box::new(path.clone()));
+        position: Position,
+        slopes: bool,
+    ) -> impl Iterator<Item = (Direction, Position)> + '_ {
+        let current = self.get(position.x, position.y);
+        Direction::ALL.into_iter().filter_map(move |direction| {
+            let next = direction.step(position)?;
+            let allowed = |tile: Option<Tile>| match tile {
+                None | Some(Tile::Forest) => false,
+                Some(Tile::Slope(slope)) => !slopes || slope == direction,
+                Some(Tile::Path) => true,
+            };
+            let leaving = match current {
+                Some(Tile::Slope(slope)) => !slopes || slope == direction,
+                _ => true,
+            };
+            (leaving && allowed(self.get(next.x, next.y))).then_some((direction, next))
+        })
+    }
+
+    /// Tiles with more than two open neighbours, where routes can branch.
+    fn is_junction(&self, position: Position) -> bool {
+        self.moves(position, false).count() > 2
+    }
+
+    /// Compresses the maze into the corridors between junctions.
+    pub fn junctions(&self, from: Position, to: Position, slopes: bool) -> Junctions {
+        let mut graph = Graph::directed();
+        let start = graph.add_node(from);
+        let goal = graph.add_node(to);
+        let mut nodes = vec![from, to];
+        for (y, line) in self.tiles.iter().enumerate() {
+            for x in 0..line.len() {
+                let position = Position { x, y };
+                if self.get(x, y) != Some(Tile::Forest) && self.is_junction(position) {
+                    graph.add_node(position);
+                    nodes.push(position);
                 }
-            } else {
-                for direction in direction_finder(&path, self) {
-                    let mut new_path = path.clone();
-                    new_path.move_to(direction);
-                    queue.push(new_path);
+            }
+        }
+
+        for node in nodes {
+            if node == to {
+                continue;
+            }
+            for (_, first) in self.moves(node, slopes) {
+                let (mut previous, mut corridor) = (node, vec![first]);
+                let end = loop {
+                    let current = *corridor.last().unwrap();
+                    if graph.id(&current).is_some() {
+                        break Some(current);
+                    }
+                    let next = self
+                        .moves(current, slopes)
+                        .map(|(_, next)| next)
+                        .find(|next| *next != previous);
+                    match next {
+                        Some(next) => {
+                            previous = current;
+                            corridor.push(next);
+                        }
+                        None => break None,
+                    }
+                };
+                if let Some(end) = end.filter(|end| *end != node) {
+                    graph.add_edge(node, end, corridor);
                 }
             }
         }
+        Junctions { graph, start, goal }
+    }
 
-        longest
+    /// The map with `route` drawn as `O`. With `ansi`, the route is
+    /// highlighted and the forest dimmed using terminal colours.
+    pub fn render(&self, route: &[Position], ansi: bool) -> String {
+        let on_route = route.iter().copied().collect::<HashSet<_>>();
+        let mut rendered = String::new();
+        for (y, line) in self.tiles.iter().enumerate() {
+            for (x, tile) in line.iter().enumerate() {
+                let cell = match (on_route.contains(&Position { x, y }), ansi) {
+                    (true, true) => "\x1b[1;33mO\x1b[0m".to_string(),
+                    (true, false) => "O".to_string(),
+                    (false, true) if *tile == Tile::Forest => format!("\x1b[2;32m{tile}\x1b[0m"),
+                    (false, _) => tile.to_string(),
+                };
+                rendered.push_str(&cell);
+            }
+            rendered.push('\n');
+        }
+        rendered
     }
 }
 
 impl FromStr for Map {
-    type Err = ();
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let tiles = s
-            .lines()
-            .map(|line| line.chars().map(Tile::from).collect())
-            .collect();
-        Ok(Map { tiles })
+        Ok(Map { tiles: grid(s)? })
     }
 }
 
@@ -343,6 +471,111 @@ mod tests {
     #[test]
     fn test_part2_challenge() {
         let input = read_input(23, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        assert_eq!(Day.part2(&input), Some(6442));
+    }
+
+    #[test]
+    fn test_junctions() {
+        let map: Map = read_input(23, true, 1).unwrap().parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        let junctions = map.junctions(from, to, true);
+        // start, goal and seven branching points
+        let graph = junctions.graph();
+        assert_eq!(graph.len(), 9);
+        assert_eq!(graph.neighbors(junctions.start()).count(), 1);
+        assert_eq!(*graph.key(junctions.goal()), to);
+        assert_eq!(junctions.longest_path(false), Some(94));
+        assert_eq!(
+            map.junctions(from, to, false).longest_path(false),
+            Some(154)
+        );
+
+        let input = read_input(23, false, 1).unwrap();
+        let map: Map = input.parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        assert_eq!(
+            map.junctions(from, to, true).longest_path(false),
+            Some(2094)
+        );
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        let map: Map = "#.###\n#.#.#\n###.#".parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        assert_eq!(map.junctions(from, to, false).longest_path(true), None);
+    }
+
+    #[test]
+    fn test_too_many_junctions() {
+        // Every tile of an open field is a junction
+        let mut field = vec![format!("#.{}", "#".repeat(12))];
+        field.extend((0..12).map(|_| format!("#{}#", ".".repeat(12))));
+        field.push(format!("{}.#", "#".repeat(12)));
+        let map: Map = field.join("\n").parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        let junctions = map.junctions(from, to, false);
+        assert!(junctions.graph.len() > 128);
+        assert_eq!(junctions.longest_path(true), None);
+        assert_eq!(junctions.longest_route(false), None);
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let map: Map = read_input(23, true, 1).unwrap().parse().unwrap();
+        assert_eq!(
+            map.endpoints(),
+            Some((Position { x: 1, y: 0 }, Position { x: 21, y: 22 }))
+        );
+        // Gaps on the sides instead of the top and bottom
+        let map: Map = "#####\n....#\n###.#\n#....\n#####".parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        assert_eq!(
+            (from, to),
+            (Position { x: 0, y: 1 }, Position { x: 4, y: 3 })
+        );
+        assert_eq!(map.junctions(from, to, true).longest_path(false), Some(6));
+        assert!("#.#.#\n#...#\n#.###"
+            .parse::<Map>()
+            .unwrap()
+            .endpoints()
+            .is_none());
+
+        // Explicit endpoints, walking the example backwards
+        let map: Map = read_input(23, true, 2).unwrap().parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        assert_eq!(map.junctions(to, from, false).longest_path(true), Some(154));
+        assert_eq!(map.junctions(to, from, true).longest_path(true), None);
+    }
+
+    #[test]
+    fn test_render_route() {
+        let map: Map = read_input(23, true, 1).unwrap().parse().unwrap();
+        let (from, to) = map.endpoints().unwrap();
+        let route = map.junctions(from, to, true).longest_route(false).unwrap();
+        assert_eq!(route.len(), 95);
+        assert_eq!((route[0], route[94]), (from, to));
+        assert_eq!(route.iter().collect::<HashSet<_>>().len(), 95);
+        for pair in route.windows(2) {
+            assert_eq!(
+                pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y),
+                1
+            );
+        }
+
+        let plain = map.render(&route, false);
+        assert_eq!(plain.matches('O').count(), 95);
+        assert!(plain.starts_with("#O#####"));
+        assert!(!plain.contains('\x1b'));
+        let ansi = map.render(&route, true);
+        assert_eq!(ansi.matches("\x1b[1;33mO").count(), 95);
+
+        let options = VisualizeOptions {
+            part2: true,
+            ..Default::default()
+        };
+        let input = read_input(23, true, 2).unwrap();
+        let visualization = Day.visualize(&input, &options).unwrap();
+        assert!(visualization.starts_with("Longest hike: 154 steps\n"));
     }
 }
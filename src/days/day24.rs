@@ -1,102 +1,369 @@
-use nalgebra::{Vector2, Vector3, Matrix2};
-use std::str::FromStr;
+use std::{cmp::Ordering, str::FromStr};
 
 use crate::solution::Solution;
+use crate::utils::math::reduce;
+use crate::utils::parse::{lines, Cursor, ParseError};
 
 pub struct Day;
 
 impl Solution for Day {
     fn part1(&self, input: &str) -> Option<usize> {
-        let hail = input
-            .lines()
-            .map(|l| l.trim().parse::<Hailstone>().unwrap())
-            .collect::<Vec<_>>();
-        let mut intersections = vec![];
-        for i in 0..hail.len() {
-            for j in i + 1..hail.len() {
-                if let Some(intersection) = hail[i].intersects_2s(&hail[j]) {
-                    intersections.push((intersection, i, j));
-                }
+        let hail = lines::<Hailstone>(input).unwrap();
+        Some(count_crossings(&hail, TEST_AREA))
+    }
+
+    fn part2(&self, input: &str) -> Option<usize> {
+        let hail = lines::<Hailstone>(input).unwrap();
+        let rock = find_rock(&hail)?;
+        usize::try_from(rock.position.iter().sum::<i64>()).ok()
+    }
+}
+
+/// Lower and upper bound of both x and y of the test area of the real input.
+const TEST_AREA: (i64, i64) = (200000000000000, 400000000000000);
+
+/// Number of pairs of hailstones whose future paths cross inside the square
+/// `bounds` (inclusive) in the x/y plane.
+pub fn count_crossings(hail: &[Hailstone], bounds: (i64, i64)) -> usize {
+    (0..hail.len())
+        .flat_map(|i| (i + 1..hail.len()).map(move |j| (i, j)))
+        .filter(|(i, j)| hail[*i].paths_cross_in(&hail[*j], bounds))
+        .count()
+}
+
+/// Exact fraction with a positive denominator.
+#[derive(Debug, Clone, Copy)]
+pub struct Ratio {
+    num: i128,
+    den: i128,
+}
+
+impl Ratio {
+    pub fn new(num: i128, den: i128) -> Self {
+        assert!(den != 0, "denominator must not be zero");
+        let (num, den) = reduce(num, den);
+        Self { num, den }
+    }
+
+    pub fn numerator(&self) -> i128 {
+        self.num
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.den
+    }
+}
+
+impl From<i64> for Ratio {
+    fn from(value: i64) -> Self {
+        Self {
+            num: value as i128,
+            den: 1,
+        }
+    }
+}
+
+impl PartialEq for Ratio {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ratio {}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num * other.den).cmp(&(other.num * self.den))
+    }
+}
+
+fn cross_2d(a: [i128; 2], b: [i128; 2]) -> i128 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn dot_2d(a: [i128; 2], b: [i128; 2]) -> i128 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+/// How the lines of two hailstones in the x/y plane relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crossing {
+    /// The lines meet in `(x, y)`, which the first hailstone reaches at time
+    /// `t` and the second one at time `s`. Negative times lie in the past.
+    Point {
+        x: Ratio,
+        y: Ratio,
+        t: Ratio,
+        s: Ratio,
+    },
+    Parallel,
+    /// Both hailstones move along the same line.
+    Coincident,
+}
+
+/// Position and velocity of the thrown rock at time 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rock {
+    pub position: [i64; 3],
+    pub velocity: [i64; 3],
+}
+
+impl Rock {
+    pub fn position_at(&self, time: i64) -> [i64; 3] {
+        [0, 1, 2].map(|axis| self.position[axis] + time * self.velocity[axis])
+    }
+
+    /// Non-negative integer time at which the rock and `hailstone` are at the
+    /// same position, if they ever are.
+    pub fn collision_time(&self, hailstone: &Hailstone) -> Option<i64> {
+        let mut time = None;
+        for axis in 0..3 {
+            let distance = (hailstone.position[axis] - self.position[axis]) as i128;
+            let speed = (self.velocity[axis] - hailstone.velocity[axis]) as i128;
+            match (speed, time) {
+                (0, _) if distance != 0 => return None,
+                (0, _) => {}
+                (_, None) if distance % speed == 0 => time = Some(distance / speed),
+                (_, None) => return None,
+                (_, Some(t)) if t * speed != distance => return None,
+                (_, Some(_)) => {}
             }
         }
-        let intersections = intersections
-            .iter()
-            .filter(|(intersection, _, _)| in_bounds_2d(*intersection, (200000000000000.0, 400000000000000.0)))
-            .collect::<Vec<_>>();
-        Some(intersections.len())
+        let time = i64::try_from(time.unwrap_or(0)).ok()?;
+        (time >= 0).then_some(time)
     }
 
-    fn part2(&self, _input: &str) -> Option<usize> {
-        None
+    /// Collision time for every hailstone, or `None` if one of them is missed.
+    pub fn collision_times(&self, hail: &[Hailstone]) -> Option<Vec<i64>> {
+        hail.iter().map(|h| self.collision_time(h)).collect()
+    }
+}
+
+/// Finds the rock that hits every hailstone.
+///
+/// For the rock `P + tV` and a hailstone `p + tv`, `(P - p) × (V - v) = 0`.
+/// The only nonlinear term `P × V` is the same for all hailstones, so the
+/// difference of two such equations is linear in `P` and `V`. Two pairs give
+/// six equations, which are solved modulo a prime larger than twice every
+/// coordinate. The candidate is then checked exactly against all hailstones,
+/// which also rejects rocks that would need non-integer coordinates.
+pub fn find_rock(hail: &[Hailstone]) -> Option<Rock> {
+    for i in 1..hail.len() {
+        for j in i + 1..hail.len() {
+            let mut system = [[0; 7]; 6];
+            system[..3].copy_from_slice(&pair_equations(&hail[0], &hail[i]));
+            system[3..].copy_from_slice(&pair_equations(&hail[0], &hail[j]));
+            let Some(solution) = solve_modular(system) else {
+                continue;
+            };
+            let rock = Rock {
+                position: [solution[0], solution[1], solution[2]],
+                velocity: [solution[3], solution[4], solution[5]],
+            };
+            if rock.collision_times(hail).is_some() {
+                return Some(rock);
+            }
+        }
     }
+    None
 }
 
-fn in_bounds_2d(intersection: Vector2<f64>, range: (f64, f64)) -> bool {
-    intersection.x >= range.0
-        && intersection.x <= range.1
-        && intersection.y >= range.0
-        && intersection.y <= range.1
+/// Rows `[Px, Py, Pz, Vx, Vy, Vz | rhs]` of `P × (vb - va) + (pb - pa) × V = pb × vb - pa × va`.
+fn pair_equations(a: &Hailstone, b: &Hailstone) -> [[i128; 7]; 3] {
+    let [pa, va, pb, vb] =
+        [a.position, a.velocity, b.position, b.velocity].map(|v| v.map(|c| c as i128));
+    let dv = [0, 1, 2].map(|k| vb[k] - va[k]);
+    let dp = [0, 1, 2].map(|k| pb[k] - pa[k]);
+    let cross = |p: [i128; 3], v: [i128; 3]| {
+        [
+            p[1] * v[2] - p[2] * v[1],
+            p[2] * v[0] - p[0] * v[2],
+            p[0] * v[1] - p[1] * v[0],
+        ]
+    };
+    let (ca, cb) = (cross(pa, va), cross(pb, vb));
+    let rhs = [0, 1, 2].map(|k| cb[k] - ca[k]);
+    [
+        [0, dv[2], -dv[1], 0, -dp[2], dp[1], rhs[0]],
+        [-dv[2], 0, dv[0], dp[2], 0, -dp[0], rhs[1]],
+        [dv[1], -dv[0], 0, -dp[1], dp[0], 0, rhs[2]],
+    ]
 }
 
-fn solve_system(a: Vector2<f64>, b: Vector2<f64>, c: Vector2<f64>) -> Option<Vector2<f64>> {
-    // Hier wird `nalgebra` verwendet, um das Gleichungssystem zu lösen.
-    // Falls es keine Lösung gibt, wird `None` zurückgegeben.
-    let x = Matrix2::new(a.x, b.x, a.y, b.y).qr().solve(&c)?;
+/// Mersenne prime 2^61 - 1, products of two residues fit into an `i128`.
+const PRIME: i128 = (1 << 61) - 1;
+
+/// Gauss-Jordan elimination over the integers modulo [`PRIME`]. Residues are
+/// mapped back to the symmetric range around zero.
+fn solve_modular(mut system: [[i128; 7]; 6]) -> Option<[i64; 6]> {
+    for row in system.iter_mut() {
+        for value in row.iter_mut() {
+            *value = value.rem_euclid(PRIME);
+        }
+    }
+    for col in 0..6 {
+        let pivot = (col..6).find(|row| system[*row][col] != 0)?;
+        system.swap(col, pivot);
+        let inverse = pow_mod(system[col][col], PRIME - 2);
+        for value in system[col].iter_mut() {
+            *value = *value * inverse % PRIME;
+        }
+        let pivot_row = system[col];
+        for (row, equation) in system.iter_mut().enumerate() {
+            let factor = equation[col];
+            if row == col || factor == 0 {
+                continue;
+            }
+            for (value, pivot) in equation.iter_mut().zip(pivot_row) {
+                *value = (*value - factor * pivot).rem_euclid(PRIME);
+            }
+        }
+    }
+    let mut solution = [0; 6];
+    for (value, row) in solution.iter_mut().zip(system) {
+        let residue = if row[6] > PRIME / 2 {
+            row[6] - PRIME
+        } else {
+            row[6]
+        };
+        *value = i64::try_from(residue).ok()?;
+    }
+    Some(solution)
+}
 
-    Some(x)
+fn pow_mod(mut base: i128, mut exponent: i128) -> i128 {
+    let mut result = 1;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % PRIME;
+        }
+        base = base * base % PRIME;
+        exponent >>= 1;
+    }
+    result
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Hailstone {
-    position: Vector3<f64>,
-    velocity: Vector3<f64>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hailstone {
+    pub position: [i64; 3],
+    pub velocity: [i64; 3],
 }
 
 impl Hailstone {
-    fn position_2d(&self) -> Vector2<f64> {
-        Vector2::new(self.position.x, self.position.y)
+    fn position_2d(&self) -> [i128; 2] {
+        [self.position[0] as i128, self.position[1] as i128]
     }
 
-    fn velocity_2d(&self) -> Vector2<f64> {
-        Vector2::new(self.velocity.x, self.velocity.y)
+    fn velocity_2d(&self) -> [i128; 2] {
+        [self.velocity[0] as i128, self.velocity[1] as i128]
     }
 
-    pub fn intersects_2s(&self, other: &Self) -> Option<Vector2<f64>> {
-        let a = self.velocity_2d();
-        let b = -other.velocity_2d();
-        let c: Vector2<f64> = other.position_2d() - self.position_2d();
-
-        let t_values = solve_system(a, b, c)?;
+    /// Exact intersection of the lines both hailstones move along, ignoring z.
+    pub fn crossing_2d(&self, other: &Self) -> Crossing {
+        let (pa, va) = (self.position_2d(), self.velocity_2d());
+        let (pb, vb) = (other.position_2d(), other.velocity_2d());
+        let dp = [pb[0] - pa[0], pb[1] - pa[1]];
+        let det = cross_2d(va, vb);
+        if det == 0 {
+            return if cross_2d(dp, va) == 0 && cross_2d(dp, vb) == 0 {
+                Crossing::Coincident
+            } else {
+                Crossing::Parallel
+            };
+        }
+        let t = cross_2d(dp, vb);
+        Crossing::Point {
+            x: Ratio::new(pa[0] * det + t * va[0], det),
+            y: Ratio::new(pa[1] * det + t * va[1], det),
+            t: Ratio::new(t, det),
+            s: Ratio::new(cross_2d(dp, va), det),
+        }
+    }
 
-        let intersection_point = self.position_2d() + t_values.x * self.velocity_2d();
+    /// Whether the future paths of both hailstones share a point inside the
+    /// square `bounds` (inclusive), ignoring z.
+    pub fn paths_cross_in(&self, other: &Self, bounds: (i64, i64)) -> bool {
+        let (low, high) = (Ratio::from(bounds.0), Ratio::from(bounds.1));
+        match self.crossing_2d(other) {
+            Crossing::Point { x, y, t, s } => {
+                let zero = Ratio::from(0);
+                t >= zero && s >= zero && low <= x && x <= high && low <= y && y <= high
+            }
+            Crossing::Parallel => false,
+            Crossing::Coincident => self.shared_ray_in(other, bounds),
+        }
+    }
 
-        // Test if intersection was in the past
-        if t_values.x < 0.0 || t_values.y < 0.0 {
-            return None;
+    /// For hailstones on the same line: whether the overlap of both forward
+    /// rays intersects the square `bounds`. The line is parameterized by the
+    /// time of the moving hailstone `a`.
+    fn shared_ray_in(&self, other: &Self, bounds: (i64, i64)) -> bool {
+        let (a, b) = if self.velocity_2d() == [0, 0] {
+            (other, self)
+        } else {
+            (self, other)
+        };
+        let (pa, va) = (a.position_2d(), a.velocity_2d());
+        if va == [0, 0] {
+            // Both stand still on the same spot
+            return [pa[0], pa[1]]
+                .iter()
+                .all(|c| (bounds.0 as i128..=bounds.1 as i128).contains(c));
         }
+        let (pb, vb) = (b.position_2d(), b.velocity_2d());
+        let speed = dot_2d(va, va);
+        let start_b = Ratio::new(dot_2d([pb[0] - pa[0], pb[1] - pa[1]], va), speed);
 
-        Some(intersection_point)
+        let mut lower = Some(Ratio::from(0));
+        let mut upper = None;
+        let mut restrict = |from: Option<Ratio>, to: Option<Ratio>| {
+            lower = lower.max(from);
+            if let Some(to) = to {
+                upper = Some(upper.map_or(to, |upper: Ratio| upper.min(to)));
+            }
+        };
+        match dot_2d(vb, va).signum() {
+            1 => restrict(Some(start_b), None),
+            -1 => restrict(None, Some(start_b)),
+            _ => restrict(Some(start_b), Some(start_b)),
+        }
+        for axis in 0..2 {
+            let (p, v) = (pa[axis], va[axis]);
+            let (low, high) = (bounds.0 as i128 - p, bounds.1 as i128 - p);
+            match v.signum() {
+                0 if low > 0 || high < 0 => return false,
+                0 => {}
+                1 => restrict(Some(Ratio::new(low, v)), Some(Ratio::new(high, v))),
+                _ => restrict(Some(Ratio::new(high, v)), Some(Ratio::new(low, v))),
+            }
+        }
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => lower <= upper,
+            _ => true,
+        }
     }
 }
 
 impl FromStr for Hailstone {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut split = s.split("@");
-        let position_numbers = split
-            .next()
-            .unwrap()
-            .split(", ")
-            .map(|s| s.trim().parse().unwrap());
-        let position = Vector3::from_iterator(position_numbers);
-        let velocity_numbers = split
-            .next()
-            .unwrap()
-            .split(", ")
-            .map(|s| s.trim().parse().unwrap());
-        let velocity = Vector3::from_iterator(velocity_numbers);
-        Ok(Hailstone { position, velocity })
+        let mut cursor = Cursor::new(s);
+        let position = cursor.sep_by(",", Cursor::int::<i64>)?;
+        cursor.literal("@")?;
+        let velocity = cursor.sep_by(",", Cursor::int::<i64>)?;
+        cursor.end()?;
+        let expected = || ParseError::new(0, "expected three coordinates per vector");
+        Ok(Hailstone {
+            position: position.try_into().map_err(|_| expected())?,
+            velocity: velocity.try_into().map_err(|_| expected())?,
+        })
     }
 }
 
@@ -108,23 +375,70 @@ mod tests {
 
     #[test]
     fn test_part1_example() {
-        let input = read_input(0, true, 1).unwrap();
-        assert_eq!(Day.part1(&input), None);
+        let input = read_input(24, true, 1).unwrap();
+        assert_eq!(Day.part1(&input), Some(0));
+        let hail = lines::<Hailstone>(&input).unwrap();
+        assert_eq!(count_crossings(&hail, (7, 27)), 2);
     }
     #[test]
     fn test_part1_challenge() {
-        let input = read_input(0, false, 1).unwrap();
-        assert_eq!(Day.part1(&input), None);
+        let input = read_input(24, false, 1).unwrap();
+        assert_eq!(Day.part1(&input), Some(12740));
     }
 
     #[test]
     fn test_part2_example() {
-        let input = read_input(0, true, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        let input = read_input(24, true, 2).unwrap();
+        assert_eq!(Day.part2(&input), Some(47));
     }
     #[test]
     fn test_part2_challenge() {
-        let input = read_input(0, false, 2).unwrap();
-        assert_eq!(Day.part2(&input), None);
+        let input = read_input(24, false, 2).unwrap();
+        assert_eq!(Day.part2(&input), Some(741991571910536));
+    }
+
+    #[test]
+    fn test_crossings() {
+        let hail = lines::<Hailstone>(&read_input(24, true, 1).unwrap()).unwrap();
+        let Crossing::Point { x, y, t, s } = hail[0].crossing_2d(&hail[1]) else {
+            panic!("expected a crossing point");
+        };
+        // x = 14.333, y = 15.333
+        assert_eq!((x, y), (Ratio::new(43, 3), Ratio::new(46, 3)));
+        assert!(t > Ratio::from(0) && s > Ratio::from(0));
+        assert_eq!(hail[1].crossing_2d(&hail[2]), Crossing::Parallel);
+
+        let stone = |s: &str| s.parse::<Hailstone>().unwrap();
+        let a = stone("0, 0, 0 @ 1, 1, 0");
+        assert_eq!(
+            a.crossing_2d(&stone("5, 5, 3 @ -2, -2, 1")),
+            Crossing::Coincident
+        );
+        // head-on on the same line, meeting between 0 and 5
+        assert!(a.paths_cross_in(&stone("5, 5, 3 @ -2, -2, 1"), (1, 2)));
+        // moving apart
+        assert!(!a.paths_cross_in(&stone("-5, -5, 3 @ -2, -2, 1"), (-10, 10)));
+        // same direction, shared part starts at 5
+        assert!(a.paths_cross_in(&stone("5, 5, 3 @ 2, 2, 1"), (6, 7)));
+        assert!(!a.paths_cross_in(&stone("5, 5, 3 @ 2, 2, 1"), (1, 4)));
+        // boundary points count
+        assert!(a.paths_cross_in(&stone("4, 0, 0 @ -1, 1, 0"), (2, 2)));
+        assert!(!a.paths_cross_in(&stone("4, 0, 0 @ -1, 1, 0"), (3, 4)));
+    }
+
+    #[test]
+    fn test_rock_trajectory() {
+        let hail = lines::<Hailstone>(&read_input(24, true, 2).unwrap()).unwrap();
+        let rock = find_rock(&hail).unwrap();
+        assert_eq!(rock.position, [24, 13, 10]);
+        assert_eq!(rock.velocity, [-3, 1, 2]);
+        assert_eq!(rock.collision_times(&hail), Some(vec![5, 3, 4, 6, 1]));
+        assert_eq!(rock.position_at(5), [9, 18, 20]);
+
+        let missed = Rock {
+            position: [24, 13, 10],
+            velocity: [-3, 1, 1],
+        };
+        assert_eq!(missed.collision_time(&hail[0]), None);
     }
 }
@@ -3,7 +3,7 @@ pub mod days;
 pub mod solution;
 pub mod utils;
 
-use solution::Solution;
+use solution::{Solution, VisualizeOptions};
 use days::*;
 
 #[derive(Parser, Debug)]
@@ -15,6 +15,30 @@ struct Args {
     /// Number of times to greet
     #[arg(short, long, default_value_t = false)]
     example: bool,
+
+    /// Print a visualization of the input instead of solving
+    #[arg(short, long, default_value_t = false)]
+    visualize: bool,
+
+    /// Simulation steps to run before visualizing
+    #[arg(short, long)]
+    steps: Option<usize>,
+
+    /// Part whose input and rules to visualize
+    #[arg(short, long, default_value_t = 1)]
+    part: u8,
+
+    /// Visualize without ANSI colours
+    #[arg(long, default_value_t = false)]
+    plain: bool,
+
+    /// Visualize as a PPM image on stdout
+    #[arg(long, default_value_t = false)]
+    image: bool,
+
+    /// Day specific starting point to visualize
+    #[arg(long)]
+    entry: Option<String>,
 }
 
 fn main() {
@@ -49,6 +73,23 @@ fn main() {
         _ => None,
     };
     if let Some(solution) = solution {
+        if args.visualize {
+            let options = VisualizeOptions {
+                steps: args.steps,
+                part2: args.part == 2,
+                plain: args.plain,
+                image: args.image,
+                entry: args.entry,
+            };
+            let visualization = utils::read_input(day, example, args.part)
+                .map(|input| solution.visualize(&input, &options));
+            match visualization {
+                Some(Some(visualization)) => println!("{visualization}"),
+                Some(None) => println!("Visualization not implemented for day {day}"),
+                None => println!("No input found for day {day} (example: {example})"),
+            }
+            return;
+        }
         println!("Day {}:", day);
         if let Some(input) = utils::read_input(day, example, 1) {
             let start = std::time::Instant::now();
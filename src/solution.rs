@@ -1,3 +1,18 @@
+/// Settings for [`Solution::visualize`].
+#[derive(Debug, Clone, Default)]
+pub struct VisualizeOptions {
+    /// Number of simulation steps to run before rendering, for days that simulate something.
+    pub steps: Option<usize>,
+    /// Use the rules of part 2, for days where they change what is rendered.
+    pub part2: bool,
+    /// Render plain text without ANSI colours.
+    pub plain: bool,
+    /// Render an image instead of text, for days that support it.
+    pub image: bool,
+    /// Day specific starting point, like `row,col,direction` for day 16.
+    pub entry: Option<String>,
+}
+
 pub trait Solution {
     fn part1(&self, _input: &str) -> Option<usize> {
         None
@@ -5,4 +20,8 @@ pub trait Solution {
     fn part2(&self, _input: &str) -> Option<usize> {
         None
     }
+    /// Renders the parsed input in a day specific format.
+    fn visualize(&self, _input: &str, _options: &VisualizeOptions) -> Option<String> {
+        None
+    }
 }
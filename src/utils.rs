@@ -1,3 +1,11 @@
+pub mod cycle;
+pub mod graph;
+pub mod math;
+pub mod parse;
+pub mod poly;
+pub mod polygon;
+pub mod random;
+
 use std::fs::read_to_string;
 use std::path::Path;
 
@@ -0,0 +1,240 @@
+use std::{
+    collections::{hash_map::DefaultHasher, hash_map::Entry, HashMap},
+    hash::{Hash, Hasher},
+};
+
+/// A detected cycle: the state at index `prefix` reappears every `period` steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    /// Smallest iteration index that has the same state as iteration `n`.
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.prefix {
+            n
+        } else {
+            self.prefix + (n - self.prefix) % self.period
+        }
+    }
+}
+
+/// The state after the requested number of steps and the cycle that was used
+/// to skip ahead, if one was found before reaching the target.
+#[derive(Debug, Clone)]
+pub struct Iterated<S> {
+    pub state: S,
+    pub cycle: Option<Cycle>,
+}
+
+/// 64 bit hash of a state, usable as key instead of cloning big states.
+pub fn fingerprint<S: Hash>(state: &S) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Applies `step` `target` times to `start`, remembering the `key` of every
+/// state to detect a cycle and skip the remaining iterations.
+///
+/// Keys are compared instead of states, so with [`fingerprint`] as key two
+/// different states with colliding hashes would be treated as equal.
+pub fn iterate_hashed<S, K>(
+    start: S,
+    target: usize,
+    mut step: impl FnMut(&mut S),
+    mut key: impl FnMut(&S) -> K,
+) -> Iterated<S>
+where
+    K: Hash + Eq,
+{
+    let mut seen = HashMap::new();
+    let mut state = start;
+    for i in 0..target {
+        match seen.entry(key(&state)) {
+            Entry::Occupied(entry) => {
+                let cycle = Cycle {
+                    prefix: *entry.get(),
+                    period: i - entry.get(),
+                };
+                for _ in 0..(target - i) % cycle.period {
+                    step(&mut state);
+                }
+                return Iterated {
+                    state,
+                    cycle: Some(cycle),
+                };
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+        }
+        step(&mut state);
+    }
+    Iterated { state, cycle: None }
+}
+
+/// Same as [`iterate_hashed`], but uses Brent's algorithm, which only keeps
+/// two states around instead of one key per iteration.
+pub fn iterate_brent<S, K>(
+    start: S,
+    target: usize,
+    mut step: impl FnMut(&mut S),
+    mut key: impl FnMut(&S) -> K,
+) -> Iterated<S>
+where
+    S: Clone,
+    K: Eq,
+{
+    match brent(start, Some(target), &mut step, &mut key) {
+        Ok((mut state, cycle)) => {
+            for _ in 0..(target - cycle.prefix) % cycle.period {
+                step(&mut state);
+            }
+            Iterated {
+                state,
+                cycle: Some(cycle),
+            }
+        }
+        Err(state) => Iterated { state, cycle: None },
+    }
+}
+
+/// Finds the cycle of a state machine that is known to repeat eventually.
+pub fn find_cycle<S, K>(
+    start: S,
+    mut step: impl FnMut(&mut S),
+    mut key: impl FnMut(&S) -> K,
+) -> Cycle
+where
+    S: Clone,
+    K: Eq,
+{
+    match brent(start, None, &mut step, &mut key) {
+        Ok((_, cycle)) => cycle,
+        Err(_) => unreachable!("Brent without target always finds a cycle"),
+    }
+}
+
+/// Returns the detected cycle together with the state at `prefix + period`,
+/// or the state at `target` if it was reached before the cycle was found.
+fn brent<S, K>(
+    start: S,
+    target: Option<usize>,
+    step: &mut impl FnMut(&mut S),
+    key: &mut impl FnMut(&S) -> K,
+) -> Result<(S, Cycle), S>
+where
+    S: Clone,
+    K: Eq,
+{
+    if target == Some(0) {
+        return Err(start);
+    }
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = key(&start);
+    let mut hare = start.clone();
+    step(&mut hare);
+    let mut index = 1;
+    loop {
+        let hare_key = key(&hare);
+        if hare_key == tortoise {
+            break;
+        }
+        if target == Some(index) {
+            return Err(hare);
+        }
+        if power == period {
+            tortoise = hare_key;
+            power *= 2;
+            period = 0;
+        }
+        step(&mut hare);
+        index += 1;
+        period += 1;
+    }
+
+    let mut tortoise = start;
+    let mut hare = tortoise.clone();
+    for _ in 0..period {
+        step(&mut hare);
+    }
+    let mut prefix = 0;
+    while key(&tortoise) != key(&hare) {
+        step(&mut tortoise);
+        step(&mut hare);
+        prefix += 1;
+    }
+    Ok((hare, Cycle { prefix, period }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(x: &mut u64) {
+        *x = (*x * *x + 1) % 255;
+    }
+
+    fn naive(start: u64, target: usize) -> u64 {
+        let mut x = start;
+        for _ in 0..target {
+            step(&mut x);
+        }
+        x
+    }
+
+    #[test]
+    fn test_hashed_and_brent_agree() {
+        for start in 0..20 {
+            for target in [0, 1, 2, 5, 17, 100, 1_000_000_000] {
+                let hashed = iterate_hashed(start, target, step, |x| *x);
+                let brent = iterate_brent(start, target, step, |x| *x);
+                assert_eq!(hashed.state, brent.state);
+                if target < 100 {
+                    assert_eq!(hashed.state, naive(start, target));
+                }
+                if let (Some(a), Some(b)) = (hashed.cycle, brent.cycle) {
+                    assert_eq!(a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        // 3 -> 10 -> 101 -> 2 -> 5 -> 26 -> 167 -> 95 -> 101
+        let cycle = find_cycle(3, step, |x| *x);
+        assert_eq!(
+            cycle,
+            Cycle {
+                prefix: 2,
+                period: 6
+            }
+        );
+        assert_eq!(cycle.reduce(1), 1);
+        assert_eq!(cycle.reduce(9), 3);
+        assert_eq!(naive(3, cycle.reduce(1_000)), naive(3, 1_000));
+    }
+
+    #[test]
+    fn test_fingerprint_key() {
+        let result = iterate_hashed(
+            vec![1u8, 2, 3],
+            1_000_000,
+            |v| v.rotate_left(1),
+            fingerprint,
+        );
+        assert_eq!(result.state, vec![2, 3, 1]);
+        assert_eq!(
+            result.cycle,
+            Some(Cycle {
+                prefix: 0,
+                period: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,485 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Display, Write},
+    hash::Hash,
+};
+
+/// Dense index of a node inside a [`Graph`], assigned in insertion order.
+pub type NodeId = usize;
+
+/// Adjacency list graph over interned node keys with edge weights `E`.
+///
+/// Undirected graphs store every edge in both directions, so `neighbors` and
+/// `predecessors` are the same for them.
+#[derive(Debug, Clone)]
+pub struct Graph<K, E = ()> {
+    directed: bool,
+    ids: HashMap<K, NodeId>,
+    keys: Vec<K>,
+    out_edges: Vec<Vec<(NodeId, E)>>,
+    in_edges: Vec<Vec<(NodeId, E)>>,
+}
+
+impl<K, E> Graph<K, E>
+where
+    K: Hash + Eq + Clone,
+    E: Clone,
+{
+    pub fn directed() -> Self {
+        Self::new(true)
+    }
+
+    pub fn undirected() -> Self {
+        Self::new(false)
+    }
+
+    fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            ids: HashMap::new(),
+            keys: vec![],
+            out_edges: vec![],
+            in_edges: vec![],
+        }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the id of `key`, adding it as a new node if necessary.
+    pub fn add_node(&mut self, key: K) -> NodeId {
+        if let Some(id) = self.ids.get(&key) {
+            return *id;
+        }
+        let id = self.keys.len();
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key);
+        self.out_edges.push(vec![]);
+        self.in_edges.push(vec![]);
+        id
+    }
+
+    /// Adds an edge between two keys, adding missing nodes on the way.
+    pub fn add_edge(&mut self, from: K, to: K, weight: E) -> (NodeId, NodeId) {
+        let from = self.add_node(from);
+        let to = self.add_node(to);
+        self.add_edge_by_id(from, to, weight);
+        (from, to)
+    }
+
+    pub fn add_edge_by_id(&mut self, from: NodeId, to: NodeId, weight: E) {
+        self.out_edges[from].push((to, weight.clone()));
+        self.in_edges[to].push((from, weight.clone()));
+        if !self.directed && from != to {
+            self.out_edges[to].push((from, weight.clone()));
+            self.in_edges[from].push((to, weight));
+        }
+    }
+
+    pub fn id(&self, key: &K) -> Option<NodeId> {
+        self.ids.get(key).copied()
+    }
+
+    pub fn key(&self, id: NodeId) -> &K {
+        &self.keys[id]
+    }
+
+    pub fn node_ids(&self) -> std::ops::Range<NodeId> {
+        0..self.len()
+    }
+
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = (NodeId, &E)> {
+        self.out_edges[id].iter().map(|(to, weight)| (*to, weight))
+    }
+
+    pub fn predecessors(&self, id: NodeId) -> impl Iterator<Item = (NodeId, &E)> {
+        self.in_edges[id]
+            .iter()
+            .map(|(from, weight)| (*from, weight))
+    }
+
+    pub fn out_degree(&self, id: NodeId) -> usize {
+        self.out_edges[id].len()
+    }
+
+    pub fn in_degree(&self, id: NodeId) -> usize {
+        self.in_edges[id].len()
+    }
+
+    /// All edges as `(from, to, weight)`. Undirected edges are listed once.
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, NodeId, &E)> {
+        self.out_edges
+            .iter()
+            .enumerate()
+            .flat_map(move |(from, edges)| {
+                edges
+                    .iter()
+                    .filter(move |(to, _)| self.directed || from <= *to)
+                    .map(move |(to, weight)| (from, *to, weight))
+            })
+    }
+
+    /// Marks every node that can be reached from any of the `sources`.
+    pub fn reachable(&self, sources: impl IntoIterator<Item = NodeId>) -> Vec<bool> {
+        let mut seen = vec![false; self.len()];
+        let mut stack: Vec<NodeId> = sources.into_iter().collect();
+        while let Some(node) = stack.pop() {
+            if seen[node] {
+                continue;
+            }
+            seen[node] = true;
+            stack.extend(
+                self.neighbors(node)
+                    .map(|(to, _)| to)
+                    .filter(|to| !seen[*to]),
+            );
+        }
+        seen
+    }
+
+    /// Kahn's algorithm. Returns `None` if the graph contains a cycle.
+    pub fn topological_sort(&self) -> Option<Vec<NodeId>> {
+        let mut in_degree = self
+            .node_ids()
+            .map(|id| self.in_degree(id))
+            .collect::<Vec<_>>();
+        let mut queue: VecDeque<NodeId> =
+            self.node_ids().filter(|id| in_degree[*id] == 0).collect();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(node) = queue.pop_front() {
+            order.push(node);
+            for (to, _) in self.neighbors(node) {
+                in_degree[to] -= 1;
+                if in_degree[to] == 0 {
+                    queue.push_back(to);
+                }
+            }
+        }
+        (order.len() == self.len()).then_some(order)
+    }
+
+    /// Tarjan's algorithm. Components are returned in reverse topological
+    /// order, i.e. a component only has edges into components listed before it.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<NodeId>> {
+        let n = self.len();
+        let mut index = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = vec![];
+        let mut components = vec![];
+        let mut counter = 0;
+
+        for root in self.node_ids() {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            let mut call_stack = vec![(root, 0)];
+            index[root] = counter;
+            low[root] = counter;
+            counter += 1;
+            stack.push(root);
+            on_stack[root] = true;
+
+            while let Some(frame) = call_stack.last_mut() {
+                let node = frame.0;
+                if let Some((next, _)) = self.out_edges[node].get(frame.1) {
+                    frame.1 += 1;
+                    let next = *next;
+                    if index[next] == usize::MAX {
+                        index[next] = counter;
+                        low[next] = counter;
+                        counter += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        call_stack.push((next, 0));
+                    } else if on_stack[next] {
+                        low[node] = low[node].min(index[next]);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some((parent, _)) = call_stack.last() {
+                    low[*parent] = low[*parent].min(low[node]);
+                }
+                if low[node] == index[node] {
+                    let mut component = vec![];
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    /// Nodes reachable from `root` in DFS post order.
+    fn postorder(&self, root: NodeId) -> Vec<NodeId> {
+        let mut seen = vec![false; self.len()];
+        let mut order = vec![];
+        let mut stack = vec![(root, 0)];
+        seen[root] = true;
+        while let Some(frame) = stack.last_mut() {
+            let node = frame.0;
+            if let Some((next, _)) = self.out_edges[node].get(frame.1) {
+                frame.1 += 1;
+                if !seen[*next] {
+                    seen[*next] = true;
+                    stack.push((*next, 0));
+                }
+            } else {
+                order.push(node);
+                stack.pop();
+            }
+        }
+        order
+    }
+
+    /// Dominator tree of all nodes reachable from `root`, using the iterative
+    /// algorithm by Cooper, Harvey and Kennedy.
+    pub fn dominators(&self, root: NodeId) -> Dominators {
+        let order = self.postorder(root);
+        let mut position = vec![usize::MAX; self.len()];
+        for (i, node) in order.iter().enumerate() {
+            position[*node] = i;
+        }
+        let mut idom = vec![None; self.len()];
+        idom[root] = Some(root);
+
+        let intersect = |idom: &[Option<NodeId>], mut a: NodeId, mut b: NodeId| {
+            while a != b {
+                while position[a] < position[b] {
+                    a = idom[a].unwrap();
+                }
+                while position[b] < position[a] {
+                    b = idom[b].unwrap();
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for node in order.iter().rev().copied().filter(|node| *node != root) {
+                let mut new_idom = None;
+                for (pred, _) in self.predecessors(node) {
+                    if idom[pred].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => pred,
+                        Some(current) => intersect(&idom, pred, current),
+                    });
+                }
+                if idom[node] != new_idom {
+                    idom[node] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        Dominators { root, idom }
+    }
+
+    /// Graphviz representation with custom attribute lists like
+    /// `shape=box, label="a"` for every node and edge.
+    pub fn to_dot_with(
+        &self,
+        node_attrs: impl Fn(NodeId, &K) -> String,
+        edge_attrs: impl Fn(NodeId, NodeId, &E) -> String,
+    ) -> String {
+        let (kind, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        let mut dot = format!("{kind} {{\n");
+        for id in self.node_ids() {
+            writeln!(dot, "    n{id} [{}];", node_attrs(id, &self.keys[id])).unwrap();
+        }
+        for (from, to, weight) in self.edges() {
+            let attrs = edge_attrs(from, to, weight);
+            if attrs.is_empty() {
+                writeln!(dot, "    n{from} {arrow} n{to};").unwrap();
+            } else {
+                writeln!(dot, "    n{from} {arrow} n{to} [{attrs}];").unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Graphviz representation labelling the nodes with their keys.
+    pub fn to_dot(&self) -> String
+    where
+        K: Display,
+    {
+        self.to_dot_with(
+            |_, key| format!("label={:?}", key.to_string()),
+            |_, _, _| String::new(),
+        )
+    }
+}
+
+/// Result of [`Graph::dominators`].
+#[derive(Debug, Clone)]
+pub struct Dominators {
+    root: NodeId,
+    idom: Vec<Option<NodeId>>,
+}
+
+impl Dominators {
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Whether `node` can be reached from the root.
+    pub fn is_reachable(&self, node: NodeId) -> bool {
+        self.idom[node].is_some()
+    }
+
+    /// `None` for the root and unreachable nodes.
+    pub fn immediate_dominator(&self, node: NodeId) -> Option<NodeId> {
+        self.idom[node].filter(|_| node != self.root)
+    }
+
+    /// All dominators of `node`, starting with `node` and ending with the root.
+    pub fn dominators(&self, node: NodeId) -> Vec<NodeId> {
+        let mut chain = vec![];
+        if !self.is_reachable(node) {
+            return chain;
+        }
+        let mut current = node;
+        chain.push(current);
+        while let Some(next) = self.immediate_dominator(current) {
+            chain.push(next);
+            current = next;
+        }
+        chain
+    }
+
+    /// Whether every path from the root to `b` passes through `a`.
+    pub fn dominates(&self, a: NodeId, b: NodeId) -> bool {
+        self.dominators(b).contains(&a)
+    }
+
+    /// Children lists of the dominator tree.
+    pub fn children(&self) -> Vec<Vec<NodeId>> {
+        let mut children = vec![vec![]; self.idom.len()];
+        for node in 0..self.idom.len() {
+            if let Some(parent) = self.immediate_dominator(node) {
+                children[parent].push(node);
+            }
+        }
+        children
+    }
+
+    /// Number of nodes dominated by each node, including itself.
+    /// Unreachable nodes dominate nothing.
+    pub fn subtree_sizes(&self) -> Vec<usize> {
+        let children = self.children();
+        let mut sizes = vec![0; self.idom.len()];
+        let mut order = vec![self.root];
+        let mut i = 0;
+        while i < order.len() {
+            order.extend(children[order[i]].iter().copied());
+            i += 1;
+        }
+        for node in order.into_iter().rev() {
+            sizes[node] = 1 + children[node].iter().map(|c| sizes[*c]).sum::<usize>();
+        }
+        sizes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diamond() -> Graph<&'static str> {
+        let mut graph = Graph::directed();
+        for (from, to) in [("a", "b"), ("a", "c"), ("b", "d"), ("c", "d"), ("d", "e")] {
+            graph.add_edge(from, to, ());
+        }
+        graph
+    }
+
+    #[test]
+    fn test_interning_and_edges() {
+        let mut graph = diamond();
+        assert_eq!(graph.len(), 5);
+        assert_eq!(graph.add_node("c"), 2);
+        assert_eq!(graph.key(3), &"d");
+        assert_eq!(graph.in_degree(graph.id(&"d").unwrap()), 2);
+        assert_eq!(graph.edges().count(), 5);
+
+        let mut undirected = Graph::undirected();
+        undirected.add_edge(1, 2, 7);
+        assert_eq!(undirected.neighbors(1).collect::<Vec<_>>(), vec![(0, &7)]);
+        assert_eq!(undirected.edges().count(), 1);
+    }
+
+    #[test]
+    fn test_reachable_and_topological_sort() {
+        let mut graph = diamond();
+        assert_eq!(graph.reachable([1]), vec![false, true, false, true, true]);
+        let order = graph.topological_sort().unwrap();
+        assert_eq!(order[0], 0);
+        assert_eq!(order[4], 4);
+        graph.add_edge("e", "b", ());
+        assert_eq!(graph.topological_sort(), None);
+    }
+
+    #[test]
+    fn test_scc() {
+        let mut graph = diamond();
+        graph.add_edge("e", "b", ());
+        let mut components = graph
+            .strongly_connected_components()
+            .into_iter()
+            .map(|mut c| {
+                c.sort();
+                c
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components.pop(), Some(vec![0]));
+        assert!(components.contains(&vec![1, 3, 4]));
+    }
+
+    #[test]
+    fn test_dominators() {
+        let graph = diamond();
+        let dominators = graph.dominators(0);
+        assert_eq!(dominators.immediate_dominator(3), Some(0));
+        assert_eq!(dominators.immediate_dominator(4), Some(3));
+        assert_eq!(dominators.dominators(4), vec![4, 3, 0]);
+        assert!(dominators.dominates(3, 4));
+        assert!(!dominators.dominates(1, 3));
+        assert_eq!(dominators.subtree_sizes(), vec![5, 1, 1, 2, 1]);
+        assert!(!graph.dominators(1).is_reachable(2));
+    }
+
+    #[test]
+    fn test_dot() {
+        let mut graph = Graph::directed();
+        graph.add_edge("broadcaster", "a", ());
+        assert_eq!(
+            graph.to_dot(),
+            "digraph {\n    n0 [label=\"broadcaster\"];\n    n1 [label=\"a\"];\n    n0 -> n1;\n}\n"
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::ops::Rem;
+
+/// Greatest common divisor of any integer type, `gcd(0, 0) == 0`. For signed
+/// integers the sign of the result depends on the inputs.
+pub fn gcd<T>(a: T, b: T) -> T
+where
+    T: Copy + Default + PartialEq + Rem<Output = T>,
+{
+    if b == T::default() {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Least common multiple, e.g. of several cycle lengths. Zero if either
+/// argument is zero.
+pub fn lcm(a: usize, b: usize) -> usize {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        a / gcd(a, b) * b
+    }
+}
+
+/// Reduces the fraction `numerator / denominator` to lowest terms with a
+/// positive denominator. The denominator must not be zero.
+pub fn reduce(numerator: i128, denominator: i128) -> (i128, i128) {
+    let divisor = gcd(numerator, denominator).abs().max(1) * denominator.signum();
+    (numerator / divisor, denominator / divisor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(7, 0), 7);
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(1, 13), 13);
+        assert_eq!(lcm(0, 0), 0);
+        assert_eq!(lcm(0, 5), 0);
+        assert_eq!(gcd(-4i128, 6).abs(), 2);
+        assert_eq!(gcd(12u64, 18), 6);
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(reduce(6, -4), (-3, 2));
+        assert_eq!(reduce(-6, -4), (3, 2));
+        assert_eq!(reduce(0, -5), (0, 1));
+        assert_eq!(reduce(7, 1), (7, 1));
+    }
+}
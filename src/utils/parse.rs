@@ -0,0 +1,429 @@
+use std::{
+    convert::Infallible,
+    fmt::{self, Display},
+    num::ParseIntError,
+    str::FromStr,
+};
+
+/// Error produced by the parsing helpers.
+///
+/// `offset` is the byte offset into the string that was handed to the parser.
+/// Use [`ParseError::line_col`] to turn it into something readable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// Moves the error by `by` bytes, used when a sub slice was parsed on its own.
+    pub fn shifted(self, by: usize) -> Self {
+        Self {
+            offset: self.offset + by,
+            message: self.message,
+        }
+    }
+
+    /// 1-based line and column of the error inside `input`.
+    pub fn line_col(&self, input: &str) -> (usize, usize) {
+        let before = &input[..self.offset.min(input.len())];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+        (line, col)
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at offset {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseIntError> for ParseError {
+    fn from(err: ParseIntError) -> Self {
+        Self::new(0, err.to_string())
+    }
+}
+
+impl From<Infallible> for ParseError {
+    fn from(err: Infallible) -> Self {
+        match err {}
+    }
+}
+
+/// Byte offset of `inner` inside `outer`. `inner` has to be a sub slice of `outer`.
+pub fn offset_in(outer: &str, inner: &str) -> usize {
+    let offset = (inner.as_ptr() as usize).wrapping_sub(outer.as_ptr() as usize);
+    assert!(offset <= outer.len(), "inner is not a sub slice of outer");
+    offset
+}
+
+/// Small position tracking reader for single records like `Game 1: 3 blue, 4 red`.
+///
+/// All readers skip leading whitespace before they look at the input.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    pub fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(self.pos, message)
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    pub fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    /// Consumes `literal` if it comes next.
+    pub fn eat(&mut self, literal: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(literal) {
+            self.pos += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn literal(&mut self, literal: &str) -> Result<(), ParseError> {
+        if self.eat(literal) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {literal:?}")))
+        }
+    }
+
+    pub fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(|c| !pred(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Reads a non empty run of alphanumeric characters or underscores.
+    pub fn word(&mut self) -> Result<&'a str, ParseError> {
+        let word = self.take_while(|c| c.is_alphanumeric() || c == '_');
+        if word.is_empty() {
+            Err(self.error("expected a word"))
+        } else {
+            Ok(word)
+        }
+    }
+
+    /// Parses everything up to the first char matching `stop` with `T::from_str`.
+    pub fn token<T>(&mut self, stop: impl Fn(char) -> bool) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Into<ParseError>,
+    {
+        self.skip_whitespace();
+        let start = self.pos;
+        let raw = self.take_while(|c| !stop(c));
+        raw.trim_end()
+            .parse()
+            .map_err(|err: T::Err| err.into().shifted(start))
+    }
+
+    pub fn uint<T>(&mut self) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Into<ParseError>,
+    {
+        self.number(false)
+    }
+
+    pub fn int<T>(&mut self) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Into<ParseError>,
+    {
+        self.number(true)
+    }
+
+    fn number<T>(&mut self, signed: bool) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: Into<ParseError>,
+    {
+        self.skip_whitespace();
+        let start = self.pos;
+        if signed && !self.eat("-") {
+            self.eat("+");
+        }
+        let digits = self.rest();
+        let len = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        if len == 0 {
+            self.pos = start;
+            return Err(self.error("expected a number"));
+        }
+        self.pos += len;
+        self.input[start..self.pos]
+            .parse()
+            .map_err(|err: T::Err| err.into().shifted(start))
+    }
+
+    /// Reads exactly `digits` hexadecimal digits.
+    pub fn hex(&mut self, digits: usize) -> Result<u64, ParseError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        match rest.get(..digits) {
+            Some(raw) if raw.chars().all(|c| c.is_ascii_hexdigit()) => {
+                self.pos += digits;
+                u64::from_str_radix(raw, 16).map_err(|err| self.error(err.to_string()))
+            }
+            _ => Err(self.error(format!("expected {digits} hex digits"))),
+        }
+    }
+
+    /// Parses one or more `item`s separated by `sep`.
+    pub fn sep_by<T>(
+        &mut self,
+        sep: &str,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![item(self)?];
+        while self.eat(sep) {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    /// Parses `item` as often as possible. A failing item is rolled back.
+    pub fn many<T>(&mut self, mut item: impl FnMut(&mut Self) -> Result<T, ParseError>) -> Vec<T> {
+        let mut items = vec![];
+        loop {
+            let start = self.pos;
+            match item(self) {
+                Ok(value) if self.pos > start => items.push(value),
+                _ => {
+                    self.pos = start;
+                    return items;
+                }
+            }
+        }
+    }
+
+    /// Fails if anything but whitespace is left.
+    pub fn end(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.pos == self.input.len() {
+            Ok(())
+        } else {
+            Err(self.error("unexpected trailing input"))
+        }
+    }
+}
+
+/// Extracts all signed integers from `s`, ignoring everything in between.
+pub fn ints<T>(s: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    let bytes = s.as_bytes();
+    let mut numbers = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        if bytes[i] == b'-' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        if bytes[i].is_ascii_digit() {
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            numbers.push(
+                s[start..i]
+                    .parse()
+                    .map_err(|err: T::Err| err.into().shifted(start))?,
+            );
+        } else {
+            i += 1;
+        }
+    }
+    Ok(numbers)
+}
+
+/// Splits `s` into blocks separated by empty lines.
+pub fn blocks(s: &str) -> impl Iterator<Item = &str> {
+    s.split("\n\n")
+        .map(|block| block.trim_matches('\n'))
+        .filter(|block| !block.is_empty())
+}
+
+pub fn pair<'a>(s: &'a str, delim: &str) -> Result<(&'a str, &'a str), ParseError> {
+    s.split_once(delim)
+        .ok_or_else(|| ParseError::new(0, format!("expected {delim:?}")))
+}
+
+/// Parses all whitespace separated fields of `s`.
+pub fn fields<T>(s: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    s.split_whitespace()
+        .map(|field| parse_at(s, field))
+        .collect()
+}
+
+/// Parses all `sep` separated entries of `s`. Entries are trimmed.
+pub fn list<T>(s: &str, sep: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    s.split(sep)
+        .map(|entry| parse_at(s, entry.trim()))
+        .collect()
+}
+
+/// Like [`list`], but requires exactly `N` entries, e.g. for `1,0,1`.
+pub fn array<T, const N: usize>(s: &str, sep: &str) -> Result<[T; N], ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    let entries = list(s, sep)?;
+    let count = entries.len();
+    entries
+        .try_into()
+        .map_err(|_| ParseError::new(0, format!("expected {N} entries, found {count}")))
+}
+
+/// Parses every line of `s`, reporting errors relative to the whole input.
+pub fn lines<T>(s: &str) -> Result<Vec<T>, ParseError>
+where
+    T: FromStr<Err = ParseError>,
+{
+    s.lines().map(|line| parse_at(s, line)).collect()
+}
+
+/// Parses a rectangular grid of characters.
+pub fn grid<T: From<char>>(s: &str) -> Result<Vec<Vec<T>>, ParseError> {
+    let mut rows: Vec<Vec<T>> = vec![];
+    for line in s.lines() {
+        let row: Vec<T> = line.chars().map(T::from).collect();
+        if let Some(first) = rows.first() {
+            if first.len() != row.len() {
+                return Err(ParseError::new(
+                    offset_in(s, line),
+                    format!("expected {} columns, found {}", first.len(), row.len()),
+                ));
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn parse_at<T>(outer: &str, inner: &str) -> Result<T, ParseError>
+where
+    T: FromStr,
+    T::Err: Into<ParseError>,
+{
+    inner
+        .parse()
+        .map_err(|err: T::Err| err.into().shifted(offset_in(outer, inner)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ints() {
+        assert_eq!(
+            ints::<i64>("19, 13, 30 @ -2,  1, -2").unwrap(),
+            vec![19, 13, 30, -2, 1, -2]
+        );
+        assert_eq!(ints::<i64>("a-b x=3..-4").unwrap(), vec![3, -4]);
+        assert_eq!(ints::<u8>("1 300").unwrap_err().offset, 2);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let blocks = blocks("a\nb\n\nc\n\n\n").collect::<Vec<_>>();
+        assert_eq!(blocks, vec!["a\nb", "c"]);
+    }
+
+    #[test]
+    fn test_fields_and_lists() {
+        assert_eq!(fields::<usize>(" 1  2 3").unwrap(), vec![1, 2, 3]);
+        assert_eq!(fields::<usize>("1 x").unwrap_err().offset, 2);
+        assert_eq!(array::<usize, 3>("1,0,1", ",").unwrap(), [1, 0, 1]);
+        assert!(array::<usize, 3>("1,0", ",").is_err());
+    }
+
+    #[test]
+    fn test_cursor() {
+        let mut cursor = Cursor::new("Game 12: 3 blue, 4 red; 1 red");
+        cursor.literal("Game").unwrap();
+        assert_eq!(cursor.uint::<usize>().unwrap(), 12);
+        cursor.literal(":").unwrap();
+        let draws = cursor
+            .sep_by(";", |c| {
+                c.sep_by(",", |c| Ok((c.uint::<usize>()?, c.word()?)))
+            })
+            .unwrap();
+        assert_eq!(draws, vec![vec![(3, "blue"), (4, "red")], vec![(1, "red")]]);
+        cursor.end().unwrap();
+
+        let mut cursor = Cursor::new("R 6 (#70c710)");
+        assert_eq!(cursor.word().unwrap(), "R");
+        assert_eq!(cursor.int::<i64>().unwrap(), 6);
+        cursor.literal("(#").unwrap();
+        assert_eq!(cursor.hex(5).unwrap(), 0x70c71);
+        assert_eq!(cursor.literal("]").unwrap_err().offset, 11);
+    }
+
+    #[test]
+    fn test_error_position() {
+        let input = "1 2\n3 x\n";
+        let err = lines::<Numbers>(input).unwrap_err();
+        assert_eq!(err.line_col(input), (2, 3));
+    }
+
+    #[derive(Debug)]
+    struct Numbers;
+
+    impl FromStr for Numbers {
+        type Err = ParseError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            fields::<usize>(s).map(|_| Numbers)
+        }
+    }
+}
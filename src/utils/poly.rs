@@ -0,0 +1,172 @@
+use std::fmt::{self, Display};
+
+use crate::utils::math::reduce;
+
+/// Errors of the exact extrapolation helpers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolyError {
+    /// No samples were given.
+    Empty,
+    /// An intermediate value did not fit into an `i128`.
+    Overflow,
+    /// Two samples share the same x coordinate.
+    DuplicateSample,
+    /// The interpolated value is not an integer.
+    NotInteger,
+}
+
+impl Display for PolyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no samples given"),
+            Self::Overflow => write!(f, "arithmetic overflow"),
+            Self::DuplicateSample => write!(f, "duplicate sample position"),
+            Self::NotInteger => write!(f, "result is not an integer"),
+        }
+    }
+}
+
+impl std::error::Error for PolyError {}
+
+/// Leading entries of the forward difference rows of equally spaced samples,
+/// i.e. `[f(0), Δf(0), Δ²f(0), ...]`. Rows that are all zero are dropped.
+pub fn differences(values: &[i64]) -> Result<Vec<i128>, PolyError> {
+    let mut row: Vec<i128> = values.iter().map(|v| *v as i128).collect();
+    let mut leading = vec![];
+    while !row.is_empty() && row.iter().any(|v| *v != 0) {
+        leading.push(row[0]);
+        row = row
+            .windows(2)
+            .map(|w| w[1].checked_sub(w[0]).ok_or(PolyError::Overflow))
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(leading)
+}
+
+/// Degree of the polynomial through the samples, if the samples determine it.
+///
+/// This is only the case if at least one difference row beyond the degree
+/// exists and is all zero. The zero polynomial is reported as degree 0.
+pub fn degree(values: &[i64]) -> Result<Option<usize>, PolyError> {
+    let leading = differences(values)?;
+    if leading.len() < values.len() {
+        Ok(Some(leading.len().saturating_sub(1)))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Value at index `n` (which may be negative or beyond the samples) of the
+/// lowest degree polynomial through `values[0..]` at indices `0, 1, 2, ...`.
+///
+/// Uses Newton's forward formula `f(n) = Σ Δᵏf(0) · C(n, k)`, which stays in
+/// integers for every integer `n`.
+pub fn extrapolate(values: &[i64], n: i64) -> Result<i128, PolyError> {
+    if values.is_empty() {
+        return Err(PolyError::Empty);
+    }
+    let n = n as i128;
+    let mut binomial: i128 = 1;
+    let mut result: i128 = 0;
+    for (k, delta) in differences(values)?.into_iter().enumerate() {
+        let k = k as i128;
+        if k > 0 {
+            binomial = binomial.checked_mul(n - k + 1).ok_or(PolyError::Overflow)? / k;
+        }
+        result = delta
+            .checked_mul(binomial)
+            .and_then(|term| result.checked_add(term))
+            .ok_or(PolyError::Overflow)?;
+    }
+    Ok(result)
+}
+
+/// Value at `x` of the lowest degree polynomial through the given `(x, y)` points.
+pub fn lagrange(points: &[(i64, i64)], x: i64) -> Result<i128, PolyError> {
+    if points.is_empty() {
+        return Err(PolyError::Empty);
+    }
+    let (mut numerator, mut denominator): (i128, i128) = (0, 1);
+    for (i, (xi, yi)) in points.iter().enumerate() {
+        let (mut term_num, mut term_den) = (*yi as i128, 1i128);
+        for (j, (xj, _)) in points.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            if xi == xj {
+                return Err(PolyError::DuplicateSample);
+            }
+            term_num = term_num
+                .checked_mul(x as i128 - *xj as i128)
+                .ok_or(PolyError::Overflow)?;
+            term_den = term_den
+                .checked_mul(*xi as i128 - *xj as i128)
+                .ok_or(PolyError::Overflow)?;
+            (term_num, term_den) = reduce(term_num, term_den);
+        }
+        numerator = numerator
+            .checked_mul(term_den)
+            .and_then(|a| {
+                term_num
+                    .checked_mul(denominator)
+                    .and_then(|b| a.checked_add(b))
+            })
+            .ok_or(PolyError::Overflow)?;
+        denominator = denominator
+            .checked_mul(term_den)
+            .ok_or(PolyError::Overflow)?;
+        (numerator, denominator) = reduce(numerator, denominator);
+    }
+    if numerator % denominator == 0 {
+        Ok(numerator / denominator)
+    } else {
+        Err(PolyError::NotInteger)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extrapolate() {
+        let values = [10, 13, 16, 21, 30, 45];
+        assert_eq!(extrapolate(&values, 6), Ok(68));
+        assert_eq!(extrapolate(&values, -1), Ok(5));
+        assert_eq!(extrapolate(&[7], 100), Ok(7));
+        assert_eq!(extrapolate(&[], 1), Err(PolyError::Empty));
+        // n² - 3
+        let squares = [-3, -2, 1, 6];
+        assert_eq!(extrapolate(&squares, 1_000_000), Ok(999_999_999_997));
+        assert_eq!(extrapolate(&squares, -5), Ok(22));
+    }
+
+    #[test]
+    fn test_degree() {
+        assert_eq!(degree(&[0, 3, 6, 9, 12, 15]), Ok(Some(1)));
+        assert_eq!(degree(&[1, 3, 6, 10, 15, 21]), Ok(Some(2)));
+        assert_eq!(degree(&[4, 4]), Ok(Some(0)));
+        assert_eq!(degree(&[1, 2, 4]), Ok(None));
+    }
+
+    #[test]
+    fn test_lagrange() {
+        // 2x² + 3x - 1 sampled at irregular positions
+        let points = [(-4, 19), (1, 4), (7, 118)];
+        assert_eq!(lagrange(&points, 0), Ok(-1));
+        assert_eq!(lagrange(&points, -10), Ok(169));
+        assert_eq!(lagrange(&[(0, 0), (2, 1)], 1), Err(PolyError::NotInteger));
+        assert_eq!(
+            lagrange(&[(1, 0), (1, 1)], 3),
+            Err(PolyError::DuplicateSample)
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(
+            extrapolate(&[0, i64::MAX, 0, i64::MIN], i64::MAX),
+            Err(PolyError::Overflow)
+        );
+    }
+}
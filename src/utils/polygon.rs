@@ -0,0 +1,231 @@
+use crate::utils::math::gcd;
+
+/// A point on the integer lattice as `(x, y)`.
+pub type Point = (i64, i64);
+
+/// Closed polygon with integer vertices. The last vertex connects back to the first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polygon {
+    vertices: Vec<Point>,
+}
+
+impl Polygon {
+    /// Builds a polygon, dropping repeated consecutive vertices and an explicit
+    /// closing vertex equal to the first one.
+    pub fn new(mut vertices: Vec<Point>) -> Self {
+        vertices.dedup();
+        while vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        Self { vertices }
+    }
+
+    /// Builds a polygon by walking the relative `(dx, dy)` moves from `start`.
+    pub fn from_moves(start: Point, moves: impl IntoIterator<Item = (i64, i64)>) -> Self {
+        let mut current = start;
+        let mut vertices = vec![start];
+        for (dx, dy) in moves {
+            current = (current.0 + dx, current.1 + dy);
+            vertices.push(current);
+        }
+        Self::new(vertices)
+    }
+
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+    }
+
+    /// Twice the signed area (shoelace formula), positive for counterclockwise
+    /// vertices in a y-up coordinate system.
+    pub fn signed_double_area(&self) -> i128 {
+        self.edges()
+            .map(|(a, b)| a.0 as i128 * b.1 as i128 - b.0 as i128 * a.1 as i128)
+            .sum()
+    }
+
+    /// Twice the enclosed area. Lattice polygons always have an integer double area.
+    pub fn double_area(&self) -> u128 {
+        self.signed_double_area().unsigned_abs()
+    }
+
+    /// Number of lattice points on the boundary.
+    pub fn boundary_points(&self) -> u128 {
+        self.edges()
+            .map(|(a, b)| gcd(a.0.abs_diff(b.0), a.1.abs_diff(b.1)) as u128)
+            .sum()
+    }
+
+    /// Number of lattice points strictly inside, using Pick's theorem
+    /// `A = I + B / 2 - 1`. Only meaningful for simple polygons, `None` for
+    /// degenerate ones (fewer than three vertices, or all of them collinear)
+    /// where the formula does not hold.
+    pub fn interior_points(&self) -> Option<u128> {
+        if self.vertices.len() < 3 {
+            return None;
+        }
+        (self.double_area() + 2)
+            .checked_sub(self.boundary_points())
+            .map(|twice| twice / 2)
+    }
+
+    pub fn on_boundary(&self, p: Point) -> bool {
+        self.edges().any(|(a, b)| on_segment(a, b, p))
+    }
+
+    /// How often the boundary winds around `p`. Zero for outside points and
+    /// undefined (but finite) for points on the boundary.
+    pub fn winding_number(&self, p: Point) -> i32 {
+        let mut winding = 0;
+        for (a, b) in self.edges() {
+            if a.1 <= p.1 {
+                if b.1 > p.1 && orientation(a, b, p) > 0 {
+                    winding += 1;
+                }
+            } else if b.1 <= p.1 && orientation(a, b, p) < 0 {
+                winding -= 1;
+            }
+        }
+        winding
+    }
+
+    /// Whether `p` lies strictly inside the polygon.
+    pub fn contains(&self, p: Point) -> bool {
+        !self.on_boundary(p) && self.winding_number(p) != 0
+    }
+
+    /// First pair of edge indices that cross, touch or overlap, if any.
+    /// Edge `i` runs from vertex `i` to vertex `i + 1`. This is quadratic in
+    /// the number of vertices.
+    pub fn self_intersection(&self) -> Option<(usize, usize)> {
+        let edges = self.edges().collect::<Vec<_>>();
+        let n = edges.len();
+        if n < 3 {
+            return None;
+        }
+        for i in 0..n {
+            for j in i + 1..n {
+                let (a, b) = edges[i];
+                let (c, d) = edges[j];
+                let intersects = if j == i + 1 {
+                    // b == c, only a fold back onto the previous edge counts
+                    orientation(a, b, d) == 0 && (on_segment(a, b, d) || on_segment(c, d, a))
+                } else if i == 0 && j == n - 1 {
+                    // d == a
+                    orientation(c, d, b) == 0 && (on_segment(c, d, b) || on_segment(a, b, c))
+                } else {
+                    segments_intersect(a, b, c, d)
+                };
+                if intersects {
+                    return Some((i, j));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn is_simple(&self) -> bool {
+        self.self_intersection().is_none()
+    }
+}
+
+/// Sign of the cross product of `b - a` and `p - a`: positive if `p` is left of `a -> b`.
+fn orientation(a: Point, b: Point, p: Point) -> i128 {
+    let cross =
+        (b.0 - a.0) as i128 * (p.1 - a.1) as i128 - (b.1 - a.1) as i128 * (p.0 - a.0) as i128;
+    cross.signum()
+}
+
+fn on_segment(a: Point, b: Point, p: Point) -> bool {
+    orientation(a, b, p) == 0
+        && a.0.min(b.0) <= p.0
+        && p.0 <= a.0.max(b.0)
+        && a.1.min(b.1) <= p.1
+        && p.1 <= a.1.max(b.1)
+}
+
+fn segments_intersect(a: Point, b: Point, c: Point, d: Point) -> bool {
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
+    (o1 * o2 < 0 && o3 * o4 < 0)
+        || on_segment(a, b, c)
+        || on_segment(a, b, d)
+        || on_segment(c, d, a)
+        || on_segment(c, d, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Polygon {
+        Polygon::new(vec![(0, 0), (4, 0), (4, 4), (0, 4), (0, 0)])
+    }
+
+    #[test]
+    fn test_area_and_pick() {
+        let square = square();
+        assert_eq!(square.vertices().len(), 4);
+        assert_eq!(square.signed_double_area(), 32);
+        assert_eq!(square.boundary_points(), 16);
+        assert_eq!(square.interior_points(), Some(9));
+
+        let triangle = Polygon::from_moves((0, 0), [(0, 5), (3, -5)]);
+        assert_eq!(triangle.signed_double_area(), -15);
+        assert_eq!(triangle.boundary_points(), 9);
+        assert_eq!(triangle.interior_points(), Some(4));
+    }
+
+    #[test]
+    fn test_degenerate() {
+        let collinear = Polygon::new(vec![(0, 0), (5, 0), (2, 0)]);
+        assert_eq!(collinear.double_area(), 0);
+        assert_eq!(collinear.boundary_points(), 10);
+        assert_eq!(collinear.interior_points(), None);
+        assert_eq!(Polygon::new(vec![(0, 0), (3, 3)]).interior_points(), None);
+        assert_eq!(Polygon::new(vec![(1, 1)]).interior_points(), None);
+    }
+
+    #[test]
+    fn test_containment() {
+        let square = square();
+        assert!(square.contains((2, 2)));
+        assert!(!square.contains((4, 2)));
+        assert!(square.on_boundary((4, 2)));
+        assert!(!square.contains((5, 2)));
+        assert_eq!(square.winding_number((1, 3)), 1);
+
+        // U shape, the notch is outside
+        let u = Polygon::new(vec![
+            (0, 0),
+            (6, 0),
+            (6, 6),
+            (4, 6),
+            (4, 2),
+            (2, 2),
+            (2, 6),
+            (0, 6),
+        ]);
+        assert_eq!(u.winding_number((3, 4)), 0);
+        assert!(!u.contains((3, 4)));
+        assert!(u.contains((1, 4)) && u.contains((5, 4)));
+        assert!(u.is_simple());
+    }
+
+    #[test]
+    fn test_self_intersection() {
+        assert!(square().is_simple());
+        let bowtie = Polygon::new(vec![(0, 0), (2, 2), (2, 0), (0, 2)]);
+        assert_eq!(bowtie.self_intersection(), Some((0, 2)));
+        let fold = Polygon::new(vec![(0, 0), (4, 0), (2, 0), (2, 2)]);
+        assert!(!fold.is_simple());
+    }
+}